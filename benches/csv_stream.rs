@@ -0,0 +1,86 @@
+//! Convert a large synthetic CSV file and report the peak memory of the process.
+//!
+//! Run with `cargo bench --bench csv_stream`, set `RCLI_BENCH_ROWS` to change the input size.
+//! Peak memory is read from `/proc/self/status`, so the memory check only runs on Linux.
+
+use std::{
+    env, fs,
+    io::{BufWriter, Write},
+    time::Instant,
+};
+
+use rcli::{process_csv, CsvReadOpts, CsvTransformOpts, OutputFormat};
+
+/// 转换过程允许增长的最大内存，与输入大小无关
+const MAX_GROWTH_KB: u64 = 64 * 1024;
+
+fn main() -> anyhow::Result<()> {
+    let rows = env::var("RCLI_BENCH_ROWS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(2_000_000);
+    let dir = env::temp_dir();
+    let input = dir.join("rcli_bench_input.csv");
+    generate(&input, rows)?;
+    let size = fs::metadata(&input)?.len();
+    println!("input: {} rows, {} MB", rows, size / 1024 / 1024);
+
+    for format in [OutputFormat::Json, OutputFormat::Ndjson, OutputFormat::Yaml] {
+        let output = dir.join(format!("rcli_bench_output.{}", format));
+        let before = peak_rss_kb();
+        let start = Instant::now();
+        process_csv(
+            fs::File::open(&input)?,
+            BufWriter::new(fs::File::create(&output)?),
+            format,
+            &CsvReadOpts::default(),
+            &CsvTransformOpts::default(),
+        )?;
+        let elapsed = start.elapsed();
+        let after = peak_rss_kb();
+        println!(
+            "{}: {:.2?}, peak rss {:?} KB -> {:?} KB",
+            format, elapsed, before, after
+        );
+        if let (Some(before), Some(after)) = (before, after) {
+            anyhow::ensure!(
+                after.saturating_sub(before) < MAX_GROWTH_KB,
+                "memory grew by {} KB while converting to {}",
+                after - before,
+                format
+            );
+        }
+        fs::remove_file(output)?;
+    }
+
+    fs::remove_file(input)?;
+    Ok(())
+}
+
+fn generate(path: &std::path::Path, rows: usize) -> anyhow::Result<()> {
+    let mut writer = BufWriter::new(fs::File::create(path)?);
+    writeln!(writer, "id,name,score,active,joined")?;
+    for i in 0..rows {
+        writeln!(
+            writer,
+            "{},player {},{}.{},{},2019-{:02}-{:02}",
+            i,
+            i,
+            i % 100,
+            i % 10,
+            i % 2 == 0,
+            i % 12 + 1,
+            i % 28 + 1
+        )?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn peak_rss_kb() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmHWM:"))
+        .and_then(|v| v.trim().trim_end_matches("kB").trim().parse().ok())
+}
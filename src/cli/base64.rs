@@ -2,9 +2,9 @@ use std::{fmt, str::FromStr};
 
 use clap::Parser;
 use enum_dispatch::enum_dispatch;
-use tracing::info;
+use std::io::Write;
 
-use crate::{get_reader, process_decode, process_encode, CmdExecutor};
+use crate::{get_input_reader, get_writer, process_decode, process_encode, CmdExecutor};
 
 use super::verify_file;
 
@@ -19,16 +19,24 @@ pub enum Base64SubCommand {
 
 #[derive(Debug, Parser)]
 pub struct Base64EncodeOpts {
+    /// Input file, `-` reads stdin; `.gz`, `.zst` and `.bz2` files are decompressed first
     #[arg(short, long, value_parser = verify_file, default_value = "-")]
     pub input: String,
+    /// Output file, `-` writes to stdout; `.gz`, `.zst` and `.bz2` files are compressed
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
     #[arg(long, value_parser = parse_base64_format, default_value = "standard")]
     pub format: Base64Format,
 }
 
 #[derive(Debug, Parser)]
 pub struct Base64DecodeOpts {
+    /// Input file, `-` reads stdin; `.gz`, `.zst` and `.bz2` files are decompressed first
     #[arg(short, long, value_parser = verify_file, default_value = "-")]
     pub input: String,
+    /// Output file, `-` writes to stdout; `.gz`, `.zst` and `.bz2` files are compressed
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
     #[arg(long, value_parser = parse_base64_format, default_value = "standard")]
     pub format: Base64Format,
 }
@@ -72,18 +80,20 @@ impl fmt::Display for Base64Format {
 
 impl CmdExecutor for Base64EncodeOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        let mut reader = get_reader(&self.input)?;
+        let mut reader = get_input_reader(&self.input)?;
         let encoded = process_encode(&mut reader, self.format)?;
-        println!("{}", encoded);
-        Ok(())
+        let mut writer = get_writer(&self.output)?;
+        writeln!(writer, "{}", encoded)?;
+        writer.finish()
     }
 }
 
 impl CmdExecutor for Base64DecodeOpts {
     async fn execute(self) -> anyhow::Result<()> {
-        let mut reader = get_reader(&self.input)?;
+        let mut reader = get_input_reader(&self.input)?;
         let decoded = process_decode(&mut reader, self.format)?;
-        info!("{}", String::from_utf8(decoded)?);
-        Ok(())
+        let mut writer = get_writer(&self.output)?;
+        writer.write_all(&decoded)?;
+        writer.finish()
     }
 }
@@ -0,0 +1,29 @@
+use clap::Parser;
+
+use crate::{process_convert, CmdExecutor};
+
+use super::{verify_file, OutputFormat};
+
+#[derive(Debug, Parser)]
+pub struct ConvertOpts {
+    #[arg(short, long, value_parser = verify_file)]
+    pub input: String,
+
+    /// Output file, `-` writes to stdout
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+
+    /// Input format, detected from the file extension or content if omitted
+    #[arg(long)]
+    pub from: Option<OutputFormat>,
+
+    /// Output format, detected from the output file extension if omitted
+    #[arg(long)]
+    pub to: Option<OutputFormat>,
+}
+
+impl CmdExecutor for ConvertOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        process_convert(&self.input, &self.output, self.from, self.to)
+    }
+}
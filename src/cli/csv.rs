@@ -1,54 +1,654 @@
-use std::{fmt, str::FromStr};
+use std::{
+    fmt,
+    io::{stdout, IsTerminal},
+    path::PathBuf,
+    str::FromStr,
+};
 
 use clap::Parser;
+use enum_dispatch::enum_dispatch;
 
-use crate::{process_csv, CmdExecutor};
+use crate::{
+    get_data_reader, get_writer, process_csv, process_csv_dedupe, process_csv_diff,
+    process_csv_join, process_csv_merge, process_csv_query, process_csv_show, process_csv_sort,
+    process_csv_split, process_csv_stats, process_csv_validate, process_to_csv, render_diff,
+    render_stats, render_table, table_name, terminal_width, CmdExecutor, SplitBy,
+};
 
-use super::verify_file;
+use super::{verify_file, verify_path};
 
-#[derive(Debug, Clone, Copy)]
+/// A data format, shared by `csv`, `convert` and `query` so they accept the same names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OutputFormat {
+    Csv,
     Json,
+    Ndjson,
     Yaml,
     Toml,
+    Cbor,
+    Msgpack,
+    Markdown,
+    Html,
+    Xlsx,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnType {
+    String,
+    Int,
+    Float,
+    Bool,
+    Date,
+}
+
+/// How arrays are written when converting documents back to CSV.
+#[derive(Debug, Clone, Copy)]
+pub enum ArrayMode {
+    /// Join scalar items into one cell with `--array-separator`
+    Join,
+    /// Emit one row per array item, zipping arrays side by side, which must have the same length
+    Explode,
+}
+
+/// Character encoding of CSV input, transcoded to UTF-8 before parsing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputEncoding {
+    /// Use the byte order mark if there is one, otherwise guess from the content
+    Auto,
+    Utf8,
+    Gbk,
+    Utf16le,
+    Utf16be,
+    Latin1,
+}
+
+/// A sort column, e.g. `age` or `age:desc`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortKey {
+    pub column: String,
+    pub descending: bool,
+}
+
+/// Which rows a join keeps besides those matching on both sides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JoinKind {
+    /// Only rows with a match on both sides
+    Inner,
+    /// Every left row
+    Left,
+    /// Every row of both sides
+    Outer,
+}
+
+/// An explicit column type given on the command line, e.g. `age=int`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnSpec {
+    pub name: String,
+    pub ty: ColumnType,
+}
+
+/// Options controlling how a CSV file is parsed, shared by every csv command.
+#[derive(Debug, Clone, Parser)]
+pub struct CsvReadOpts {
+    #[arg(short, long, value_parser = parse_delimiter, default_value = ",")]
+    pub delimiter: char,
+
+    #[arg(long, default_value_t = true, action = clap::ArgAction::Set)]
+    pub header: bool,
+
+    /// Column names to use instead of the header row, e.g. `--columns id,name`
+    #[arg(long, value_delimiter = ',')]
+    pub columns: Vec<String>,
+
+    /// Input encoding: auto, utf-8, gbk, utf-16le, utf-16be or latin1; an explicit encoding
+    /// rejects malformed input instead of replacing it
+    #[arg(long, default_value = "auto")]
+    pub encoding: InputEncoding,
+}
+
+/// Options controlling how records are typed, filtered and shaped before they are written.
+#[derive(Debug, Clone, Default, Parser)]
+pub struct CsvTransformOpts {
+    /// Keep every cell as a string instead of inferring column types from the first 1000 rows;
+    /// a later cell that doesn't fit the inferred type is kept as a string
+    #[arg(long, default_value_t = false)]
+    pub no_infer: bool,
+
+    /// Explicit column types, e.g. `--types age=int,joined=date`
+    #[arg(long, value_delimiter = ',')]
+    pub types: Vec<ColumnSpec>,
+
+    /// Columns to keep, in output order, e.g. `--select name,position`
+    #[arg(long, value_delimiter = ',')]
+    pub select: Vec<String>,
+
+    /// Rename columns, e.g. `--rename "Kit Number=number"`
+    #[arg(long, value_delimiter = ',', value_parser = parse_rename)]
+    pub rename: Vec<(String, String)>,
+
+    /// Only keep rows matching a predicate, e.g. `--where 'age>30 && nationality=="Italy"'`
+    #[arg(long = "where")]
+    pub filter: Option<String>,
+
+    /// Build nested objects and arrays from headers like `address.city` or `tags[0]`
+    #[arg(long, default_value_t = false)]
+    pub unflatten: bool,
+}
+
+/// Options for rendering a CSV file as a table with `--show`.
+#[derive(Debug, Clone, Default, Parser)]
+pub struct CsvViewOpts {
+    /// Show only the first N rows
+    #[arg(long, conflicts_with_all = ["tail", "rows"])]
+    pub head: Option<usize>,
+
+    /// Show only the last N rows
+    #[arg(long, conflicts_with = "rows")]
+    pub tail: Option<usize>,
+
+    /// Show a range of row numbers, e.g. `10..20`, `10..=20` or `10..`
+    #[arg(long)]
+    pub rows: Option<RowRange>,
+
+    /// Truncate cells wider than this many characters
+    #[arg(long, default_value_t = 40)]
+    pub max_width: usize,
+}
+
+/// A range of 1-based row numbers, both ends inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RowRange {
+    pub start: usize,
+    pub end: Option<usize>,
 }
 
 #[derive(Debug, Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 pub struct CsvOpts {
-    #[arg(short, long, value_parser = verify_file)]
-    pub input: String,
+    #[command(subcommand)]
+    pub cmd: Option<CsvSubCommand>,
+
+    #[arg(short, long, value_parser = verify_file, required = true)]
+    pub input: Option<String>,
 
+    /// Output file, `-` writes to stdout
     #[arg(short, long)]
     pub output: Option<String>,
 
     #[arg(long, default_value = "json")]
     pub format: OutputFormat,
 
-    #[arg(short, long, default_value_t = ',')]
-    pub delimiter: char,
+    #[command(flatten)]
+    pub read: CsvReadOpts,
 
-    #[arg(long, default_value_t = true)]
-    pub header: bool,
+    #[command(flatten)]
+    pub transform: CsvTransformOpts,
+
+    /// Print the file as a table instead of converting it
+    #[arg(long, default_value_t = false)]
+    pub show: bool,
+
+    #[command(flatten)]
+    pub view: CsvViewOpts,
+
+    /// Convert a document in this format back to CSV instead
+    #[arg(long)]
+    pub from: Option<OutputFormat>,
+
+    /// How arrays are written when converting back to CSV: join or explode
+    #[arg(long, default_value = "join")]
+    pub arrays: ArrayMode,
+
+    #[arg(long, default_value = ";")]
+    pub array_separator: String,
+}
+
+#[derive(Debug, Parser)]
+#[enum_dispatch(CmdExecutor)]
+pub enum CsvSubCommand {
+    #[command(about = "Show per-column statistics of a csv file")]
+    Stats(CsvStatsOpts),
+    #[command(about = "Validate a csv file against a JSON Schema or column spec")]
+    Validate(CsvValidateOpts),
+    #[command(
+        about = "Run a SQL query over csv files, each loaded as a table named after the file"
+    )]
+    Query(CsvQueryOpts),
+    #[command(about = "Sort a csv file by one or more columns")]
+    Sort(CsvSortOpts),
+    #[command(about = "Remove rows with duplicate keys, keeping the first")]
+    Dedupe(CsvDedupeOpts),
+    #[command(about = "Join two csv files on key columns")]
+    Join(CsvJoinOpts),
+    #[command(about = "Show rows added, removed and changed between two csv files")]
+    Diff(CsvDiffOpts),
+    #[command(about = "Split a csv file into chunks by row count or column value")]
+    Split(CsvSplitOpts),
+    #[command(about = "Concatenate csv files, taking the union of their columns")]
+    Merge(CsvMergeOpts),
+}
+
+/// Where sort, dedupe and join write their rows.
+#[derive(Debug, Clone, Parser)]
+pub struct CsvWriteOpts {
+    /// Output file, `-` writes to stdout
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+
+    /// Write records in this format instead of csv
+    #[arg(long)]
+    pub format: Option<OutputFormat>,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvSortOpts {
+    #[arg(short, long, value_parser = verify_file)]
+    pub input: String,
+
+    /// Columns to sort by, e.g. `--by position,age:desc`
+    #[arg(long, value_delimiter = ',', required = true)]
+    pub by: Vec<SortKey>,
+
+    /// Memory budget in MB, larger files are sorted in chunks on disk
+    #[arg(long, default_value_t = 256)]
+    pub max_memory: usize,
+
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+
+    #[command(flatten)]
+    pub write: CsvWriteOpts,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvDedupeOpts {
+    #[arg(short, long, value_parser = verify_file)]
+    pub input: String,
+
+    /// Columns that identify a row, the whole row if omitted
+    #[arg(long, value_delimiter = ',')]
+    pub key: Vec<String>,
+
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+
+    #[command(flatten)]
+    pub write: CsvWriteOpts,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvJoinOpts {
+    #[arg(value_parser = verify_file)]
+    pub left: String,
+
+    #[arg(value_parser = verify_file)]
+    pub right: String,
+
+    /// Key columns present in both files, e.g. `--on id`
+    #[arg(long, value_delimiter = ',', required = true)]
+    pub on: Vec<String>,
+
+    /// inner, left or outer
+    #[arg(long, default_value = "inner")]
+    pub how: JoinKind,
+
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+
+    #[command(flatten)]
+    pub write: CsvWriteOpts,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvStatsOpts {
+    #[arg(short, long, value_parser = verify_file)]
+    pub input: String,
+
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+
+    /// Number of most frequent values to report per column
+    #[arg(long, default_value_t = 5)]
+    pub top: usize,
+
+    #[arg(long, default_value = "table")]
+    pub format: ReportFormat,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvValidateOpts {
+    #[arg(short, long, value_parser = verify_file)]
+    pub input: String,
+
+    /// A JSON Schema describing one row, or a `{"columns": [...]}` column spec
+    #[arg(short, long, value_parser = verify_file)]
+    pub schema: String,
+
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+
+    /// Stop after this many errors
+    #[arg(long, default_value_t = 100)]
+    pub max_errors: usize,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvQueryOpts {
+    /// The SQL to run, e.g. `SELECT position, count(*) FROM juventus GROUP BY position`
+    pub sql: String,
+
+    /// CSV files to load, can be given more than once
+    #[arg(short, long, value_parser = verify_file, required = true)]
+    pub input: Vec<String>,
+
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+
+    /// Output file for --format, `-` writes to stdout
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+
+    /// Write the result in this format instead of printing a table
+    #[arg(long)]
+    pub format: Option<OutputFormat>,
+
+    /// Truncate cells wider than this many characters
+    #[arg(long, default_value_t = 40)]
+    pub max_width: usize,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvDiffOpts {
+    #[arg(value_parser = verify_file)]
+    pub old: String,
+
+    #[arg(value_parser = verify_file)]
+    pub new: String,
+
+    /// Columns identifying a row in both files, e.g. `--key id`
+    #[arg(long, value_delimiter = ',', required = true)]
+    pub key: Vec<String>,
+
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+
+    #[arg(long, default_value = "table")]
+    pub format: ReportFormat,
+
+    /// Exit with status 1 when the files differ
+    #[arg(long, default_value_t = false)]
+    pub exit_code: bool,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvSplitOpts {
+    #[arg(short, long, value_parser = verify_file)]
+    pub input: String,
+
+    /// Rows per output file
+    #[arg(long, conflicts_with = "by", required_unless_present = "by")]
+    pub rows: Option<usize>,
+
+    /// Write one file per distinct value of this column
+    #[arg(long)]
+    pub by: Option<String>,
+
+    /// Directory to write the files to
+    #[arg(long, value_parser = verify_path, default_value = ".")]
+    pub output_dir: PathBuf,
+
+    /// File name prefix, defaults to the input file name
+    #[arg(long)]
+    pub prefix: Option<String>,
+
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+}
+
+#[derive(Debug, Parser)]
+pub struct CsvMergeOpts {
+    #[arg(value_parser = verify_file, num_args = 1.., required = true)]
+    pub inputs: Vec<String>,
+
+    #[command(flatten)]
+    pub read: CsvReadOpts,
+
+    #[command(flatten)]
+    pub write: CsvWriteOpts,
+}
+
+/// How a report is printed: a table for people or JSON for scripts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportFormat {
+    Table,
+    Json,
 }
 
 impl CmdExecutor for CsvOpts {
     async fn execute(self) -> anyhow::Result<()> {
+        if let Some(cmd) = self.cmd {
+            return cmd.execute().await;
+        }
+        // 没有子命令时 clap 会保证 --input 存在
+        let Some(input) = self.input else {
+            anyhow::bail!("--input is required");
+        };
+
+        if let Some(from) = self.from {
+            let output = self.output.unwrap_or_else(|| "output.csv".to_string());
+            return process_to_csv(
+                &input,
+                &output,
+                from,
+                &self.read,
+                self.arrays,
+                &self.array_separator,
+            );
+        }
+
+        if self.show {
+            let table = process_csv_show(&input, &self.read, &self.transform, &self.view)?;
+            print!("{}", table);
+            return Ok(());
+        }
+
         let output = if let Some(output) = self.output {
             output
         } else {
             format!("output.{}", self.format)
         };
-        process_csv(&self.input, output, self.format)?;
+        let mut writer = get_writer(&output)?;
+        process_csv(
+            get_data_reader(&input)?,
+            &mut writer,
+            self.format,
+            &self.read,
+            &self.transform,
+        )?;
+        writer.finish()
+    }
+}
+
+impl CmdExecutor for CsvStatsOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let stats = process_csv_stats(&self.input, &self.read, self.top)?;
+        match self.format {
+            ReportFormat::Table => print!("{}", render_stats(&stats)),
+            ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&stats)?),
+        }
+        Ok(())
+    }
+}
+
+impl CmdExecutor for CsvValidateOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let errors = process_csv_validate(&self.input, &self.schema, &self.read, self.max_errors)?;
+        for error in &errors {
+            println!("{}", error);
+        }
+        if !errors.is_empty() {
+            anyhow::bail!(
+                "{} failed validation with {} error(s)",
+                self.input,
+                errors.len()
+            );
+        }
+        eprintln!("{} is valid", self.input);
         Ok(())
     }
 }
 
+impl CmdExecutor for CsvQueryOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let result = process_csv_query(&self.input, &self.sql, &self.read)?;
+        match self.format {
+            Some(format) => {
+                let mut writer = get_writer(&self.output)?;
+                result.write(format, &mut writer)?;
+                writer.finish()?;
+            }
+            None => print!(
+                "{}",
+                render_table(
+                    &result.columns,
+                    &result.rows,
+                    self.max_width,
+                    terminal_width()
+                )
+            ),
+        }
+        Ok(())
+    }
+}
+
+impl CmdExecutor for CsvSortOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        process_csv_sort(
+            &self.input,
+            &self.by,
+            self.max_memory.saturating_mul(1024 * 1024),
+            &self.read,
+            &self.write.output,
+            self.write.format,
+        )
+    }
+}
+
+impl CmdExecutor for CsvDedupeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        process_csv_dedupe(
+            &self.input,
+            &self.key,
+            &self.read,
+            &self.write.output,
+            self.write.format,
+        )
+    }
+}
+
+impl CmdExecutor for CsvJoinOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        process_csv_join(
+            &self.left,
+            &self.right,
+            &self.on,
+            self.how,
+            &self.read,
+            &self.write.output,
+            self.write.format,
+        )
+    }
+}
+
+impl CmdExecutor for CsvDiffOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let diff = process_csv_diff(&self.old, &self.new, &self.key, &self.read)?;
+        match self.format {
+            ReportFormat::Table => print!("{}", render_diff(&diff, stdout().is_terminal())),
+            ReportFormat::Json => println!("{}", serde_json::to_string_pretty(&diff)?),
+        }
+        if self.exit_code && !diff.is_empty() {
+            std::process::exit(1);
+        }
+        Ok(())
+    }
+}
+
+impl CmdExecutor for CsvSplitOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let by = match (self.rows, self.by) {
+            (Some(rows), _) => SplitBy::Rows(rows),
+            (None, Some(column)) => SplitBy::Column(column),
+            (None, None) => anyhow::bail!("either --rows or --by is required"),
+        };
+        let prefix = self.prefix.unwrap_or_else(|| table_name(&self.input));
+        let paths = process_csv_split(&self.input, &by, &self.read, &self.output_dir, &prefix)?;
+        for path in &paths {
+            println!("{}", path);
+        }
+        Ok(())
+    }
+}
+
+impl CmdExecutor for CsvMergeOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        process_csv_merge(
+            &self.inputs,
+            &self.read,
+            &self.write.output,
+            self.write.format,
+        )
+    }
+}
+
+impl Default for CsvReadOpts {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            header: true,
+            columns: Vec::new(),
+            encoding: InputEncoding::Auto,
+        }
+    }
+}
+
+fn parse_delimiter(s: &str) -> Result<char, anyhow::Error> {
+    let delimiter = match s {
+        "\\t" | "tab" => '\t',
+        _ => {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => c,
+                _ => anyhow::bail!("Delimiter must be a single character: {}", s),
+            }
+        }
+    };
+    anyhow::ensure!(delimiter.is_ascii(), "Delimiter must be ASCII: {}", s);
+    Ok(delimiter)
+}
+
+fn parse_rename(s: &str) -> Result<(String, String), anyhow::Error> {
+    match s.split_once('=') {
+        Some((from, to)) if !from.trim().is_empty() && !to.trim().is_empty() => {
+            Ok((from.trim().to_string(), to.trim().to_string()))
+        }
+        _ => anyhow::bail!("Invalid rename: {}, expected <column>=<new name>", s),
+    }
+}
+
 impl From<OutputFormat> for &'static str {
     fn from(format: OutputFormat) -> Self {
         match format {
+            OutputFormat::Csv => "csv",
             OutputFormat::Json => "json",
+            OutputFormat::Ndjson => "ndjson",
             OutputFormat::Yaml => "yaml",
             OutputFormat::Toml => "toml",
+            OutputFormat::Cbor => "cbor",
+            OutputFormat::Msgpack => "msgpack",
+            OutputFormat::Markdown => "md",
+            OutputFormat::Html => "html",
+            OutputFormat::Xlsx => "xlsx",
         }
     }
 }
@@ -58,9 +658,16 @@ impl FromStr for OutputFormat {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         Ok(match s.to_lowercase().as_str() {
+            "csv" => OutputFormat::Csv,
             "json" => OutputFormat::Json,
-            "yaml" => OutputFormat::Yaml,
+            "ndjson" | "jsonl" => OutputFormat::Ndjson,
+            "yaml" | "yml" => OutputFormat::Yaml,
             "toml" => OutputFormat::Toml,
+            "cbor" => OutputFormat::Cbor,
+            "msgpack" | "messagepack" => OutputFormat::Msgpack,
+            "md" | "markdown" => OutputFormat::Markdown,
+            "html" => OutputFormat::Html,
+            "xlsx" | "excel" => OutputFormat::Xlsx,
             _ => anyhow::bail!("Invalid formatter: {}", s),
         })
     }
@@ -71,3 +678,214 @@ impl fmt::Display for OutputFormat {
         write!(f, "{}", Into::<&str>::into(*self))
     }
 }
+
+impl FromStr for RowRange {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((start, end)) = s.split_once("..") else {
+            anyhow::bail!("Invalid row range: {}, expected e.g. 10..20", s);
+        };
+        let parse = |n: &str| -> anyhow::Result<Option<usize>> {
+            if n.is_empty() {
+                Ok(None)
+            } else {
+                Ok(Some(n.parse()?))
+            }
+        };
+        let start = parse(start)?.unwrap_or(1).max(1);
+        // a..b 不包含 b，a..=b 包含 b
+        let end = match end.strip_prefix('=') {
+            Some(end) => parse(end)?,
+            None => parse(end)?.map(|end| end.saturating_sub(1)),
+        };
+        Ok(RowRange { start, end })
+    }
+}
+
+impl From<ReportFormat> for &'static str {
+    fn from(format: ReportFormat) -> Self {
+        match format {
+            ReportFormat::Table => "table",
+            ReportFormat::Json => "json",
+        }
+    }
+}
+
+impl FromStr for ReportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "table" => ReportFormat::Table,
+            "json" => ReportFormat::Json,
+            _ => anyhow::bail!("Invalid report format: {}", s),
+        })
+    }
+}
+
+impl fmt::Display for ReportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+impl From<InputEncoding> for &'static str {
+    fn from(encoding: InputEncoding) -> Self {
+        match encoding {
+            InputEncoding::Auto => "auto",
+            InputEncoding::Utf8 => "utf-8",
+            InputEncoding::Gbk => "gbk",
+            InputEncoding::Utf16le => "utf-16le",
+            InputEncoding::Utf16be => "utf-16be",
+            InputEncoding::Latin1 => "latin1",
+        }
+    }
+}
+
+impl FromStr for InputEncoding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().replace('_', "-").as_str() {
+            "auto" => InputEncoding::Auto,
+            "utf-8" | "utf8" => InputEncoding::Utf8,
+            "gbk" | "gb2312" | "gb18030" => InputEncoding::Gbk,
+            "utf-16le" | "utf16le" => InputEncoding::Utf16le,
+            "utf-16be" | "utf16be" => InputEncoding::Utf16be,
+            "latin1" | "latin-1" | "iso-8859-1" | "windows-1252" => InputEncoding::Latin1,
+            _ => anyhow::bail!("Invalid encoding: {}", s),
+        })
+    }
+}
+
+impl fmt::Display for InputEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (column, descending) = match s.rsplit_once(':') {
+            Some((column, order)) => match order.to_lowercase().as_str() {
+                "asc" => (column, false),
+                "desc" => (column, true),
+                _ => anyhow::bail!("Invalid sort order: {}, expected asc or desc", order),
+            },
+            None => (s, false),
+        };
+        if column.is_empty() {
+            anyhow::bail!("Invalid sort key: {}", s);
+        }
+        Ok(SortKey {
+            column: column.to_string(),
+            descending,
+        })
+    }
+}
+
+impl From<JoinKind> for &'static str {
+    fn from(kind: JoinKind) -> Self {
+        match kind {
+            JoinKind::Inner => "inner",
+            JoinKind::Left => "left",
+            JoinKind::Outer => "outer",
+        }
+    }
+}
+
+impl FromStr for JoinKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "inner" => JoinKind::Inner,
+            "left" => JoinKind::Left,
+            "outer" | "full" => JoinKind::Outer,
+            _ => anyhow::bail!("Invalid join: {}", s),
+        })
+    }
+}
+
+impl fmt::Display for JoinKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+impl From<ArrayMode> for &'static str {
+    fn from(mode: ArrayMode) -> Self {
+        match mode {
+            ArrayMode::Join => "join",
+            ArrayMode::Explode => "explode",
+        }
+    }
+}
+
+impl FromStr for ArrayMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "join" => ArrayMode::Join,
+            "explode" => ArrayMode::Explode,
+            _ => anyhow::bail!("Invalid array mode: {}", s),
+        })
+    }
+}
+
+impl fmt::Display for ArrayMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+impl From<ColumnType> for &'static str {
+    fn from(ty: ColumnType) -> Self {
+        match ty {
+            ColumnType::String => "string",
+            ColumnType::Int => "int",
+            ColumnType::Float => "float",
+            ColumnType::Bool => "bool",
+            ColumnType::Date => "date",
+        }
+    }
+}
+
+impl FromStr for ColumnType {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s.to_lowercase().as_str() {
+            "string" | "str" => ColumnType::String,
+            "int" | "integer" => ColumnType::Int,
+            "float" | "number" => ColumnType::Float,
+            "bool" | "boolean" => ColumnType::Bool,
+            "date" => ColumnType::Date,
+            _ => anyhow::bail!("Invalid column type: {}", s),
+        })
+    }
+}
+
+impl fmt::Display for ColumnType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+impl FromStr for ColumnSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((name, ty)) = s.split_once('=') else {
+            anyhow::bail!("Invalid column type spec: {}, expected <column>=<type>", s);
+        };
+        Ok(ColumnSpec {
+            name: name.trim().to_string(),
+            ty: ty.trim().parse()?,
+        })
+    }
+}
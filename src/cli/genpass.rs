@@ -1,11 +1,137 @@
-use crate::{process_genpass, CmdExecutor};
+use crate::{
+    charset_size, entropy_bits, get_writer, load_policy, load_wordlist, process_genpass,
+    process_genpass_with_policy, process_genphrase, CmdExecutor, GeneratedPassword, PasswordWriter,
+    DEFAULT_LENGTH,
+};
 use clap::Parser;
-use zxcvbn::zxcvbn;
+use serde::Deserialize;
+use std::{fmt, io::Write, str::FromStr};
+
+use super::verify_file;
+
 #[derive(Debug, Parser)]
 pub struct GenPassOpts {
-    #[arg(short, long, default_value_t = 16)]
-    pub length: u8,
+    /// Password length [default: 16, or the nearest length a `--policy` allows]
+    #[arg(short, long, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..=4096))]
+    pub length: Option<usize>,
+
+    #[command(flatten)]
+    pub charset: CharsetOpts,
+
+    /// Generate a passphrase of this many words from `--wordlist` instead of random characters
+    #[arg(long, conflicts_with_all = ["length", "no_symbols", "no_lowercase", "no_uppercase", "no_numbers", "charset", "symbols", "exclude_similar", "exclude"])]
+    pub words: Option<usize>,
+
+    /// Separator placed between passphrase words
+    #[arg(long, default_value = "-", requires = "words")]
+    pub separator: String,
+
+    /// Capitalize the first letter of each passphrase word
+    #[arg(long, default_value_t = false, requires = "words")]
+    pub capitalize: bool,
+
+    /// TOML policy file the password must satisfy, e.g. `--policy policies/oracle.toml`
+    #[arg(long, value_parser = verify_file, conflicts_with = "words")]
+    pub policy: Option<String>,
+
+    /// Wordlist to draw passphrase words from, one word per line or in the EFF dice format,
+    /// e.g. the EFF large wordlist from https://www.eff.org/dice
+    #[arg(long, value_parser = verify_file, requires = "words")]
+    pub wordlist: Option<String>,
+
+    /// Number of passwords to generate
+    #[arg(short, long, default_value_t = 1)]
+    pub count: usize,
+
+    /// Print each password with its length, entropy and score: json or csv
+    #[arg(long)]
+    pub output_format: Option<PasswordFormat>,
+}
+
+impl CmdExecutor for GenPassOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        if self.count == 0 {
+            anyhow::bail!("--count must be at least 1");
+        }
+        let charset = &self.charset.charset;
+        if self.charset.symbols.is_some()
+            && !charset.is_empty()
+            && !charset.contains(&CharClass::Symbol)
+        {
+            anyhow::bail!("--symbols needs symbol in --charset, e.g. `--charset lower,symbol`");
+        }
+        // 每次生成一个密码及其熵，生成后立即写出
+        let mut generate: Box<dyn FnMut() -> anyhow::Result<(String, f64)>> = match self.words {
+            Some(words) => {
+                let Some(path) = self.wordlist.as_deref() else {
+                    anyhow::bail!(
+                        "no built-in wordlist is bundled in this build, pass --wordlist, \
+                         e.g. the EFF large wordlist"
+                    );
+                };
+                let wordlist = load_wordlist(path)?;
+                let entropy = entropy_bits(wordlist.len(), words);
+                Box::new(move || {
+                    let pwd =
+                        process_genphrase(words, &self.separator, self.capitalize, &wordlist)?;
+                    Ok((pwd, entropy))
+                })
+            }
+            None => {
+                let policy = self.policy.as_deref().map(load_policy).transpose()?;
+                let (length, entropy) = match &policy {
+                    Some(policy) => {
+                        let length = policy.length(self.length)?;
+                        (length, policy.entropy(length, &self.charset)?)
+                    }
+                    None => {
+                        let length = self.length.unwrap_or(DEFAULT_LENGTH);
+                        (length, entropy_bits(charset_size(&self.charset)?, length))
+                    }
+                };
+                Box::new(move || {
+                    let pwd = match &policy {
+                        Some(policy) => process_genpass_with_policy(length, &self.charset, policy)?,
+                        None => process_genpass(length, &self.charset)?,
+                    };
+                    Ok((pwd, entropy))
+                })
+            }
+        };
 
+        let mut output = get_writer("-")?;
+        if let Some(format) = self.output_format {
+            let mut writer = PasswordWriter::new(format, &mut output);
+            for _ in 0..self.count {
+                let (pwd, entropy) = generate()?;
+                writer.write(&GeneratedPassword::new(pwd, entropy)?)?;
+            }
+            writer.finish()?;
+            if format == PasswordFormat::Json {
+                writeln!(output)?;
+            }
+            return output.finish();
+        }
+        if self.count > 1 {
+            for _ in 0..self.count {
+                writeln!(output, "{}", generate()?.0)?;
+            }
+            return output.finish();
+        }
+        let (pwd, entropy) = generate()?;
+        writeln!(output, "{}", pwd)?;
+        output.finish()?;
+        // output password strength in stderr
+        let generated = GeneratedPassword::new(pwd, entropy)?;
+        eprintln!("Password strength: {}", generated.score);
+        eprintln!("Entropy: {:.1} bits", generated.entropy);
+        Ok(())
+    }
+}
+
+/// Options choosing which characters a generated password may contain.
+#[derive(Debug, Clone, Default, Parser)]
+pub struct CharsetOpts {
     #[arg(long, default_value_t = false)]
     pub no_symbols: bool,
 
@@ -17,22 +143,103 @@ pub struct GenPassOpts {
 
     #[arg(long, default_value_t = false)]
     pub no_numbers: bool,
+
+    /// Character types to use instead of the `--no-*` flags, e.g. `--charset lower,number`
+    #[arg(long, value_delimiter = ',', conflicts_with_all = ["no_symbols", "no_lowercase", "no_uppercase", "no_numbers"])]
+    pub charset: Vec<CharClass>,
+
+    /// Symbols to draw from instead of `!@#$%^&*_`
+    #[arg(long, allow_hyphen_values = true, conflicts_with = "no_symbols")]
+    pub symbols: Option<String>,
+
+    /// Leave out characters that are easy to confuse: 0, O, 1, l and I
+    #[arg(long, default_value_t = false)]
+    pub exclude_similar: bool,
+
+    /// Characters that must never appear in the password
+    #[arg(long, allow_hyphen_values = true)]
+    pub exclude: Option<String>,
 }
 
-impl CmdExecutor for GenPassOpts {
-    async fn execute(self) -> anyhow::Result<()> {
-        let pwd = process_genpass(
-            self.length,
-            self.no_uppercase,
-            self.no_lowercase,
-            self.no_numbers,
-            self.no_symbols,
-        )?;
-        println!("{}", pwd);
+/// A class of characters a password draws from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum CharClass {
+    Upper,
+    Lower,
+    Number,
+    Symbol,
+}
 
-        // output password strength in stderr
-        let estimate = zxcvbn(&pwd, &[])?;
-        eprintln!("Password strength: {}", estimate.score());
-        Ok(())
+impl From<CharClass> for &'static str {
+    fn from(class: CharClass) -> Self {
+        match class {
+            CharClass::Upper => "upper",
+            CharClass::Lower => "lower",
+            CharClass::Number => "number",
+            CharClass::Symbol => "symbol",
+        }
+    }
+}
+
+impl FromStr for CharClass {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "upper" | "uppercase" => Ok(CharClass::Upper),
+            "lower" | "lowercase" => Ok(CharClass::Lower),
+            "number" | "numbers" | "digit" | "digits" => Ok(CharClass::Number),
+            "symbol" | "symbols" => Ok(CharClass::Symbol),
+            _ => anyhow::bail!("Invalid character type: {}", s),
+        }
+    }
+}
+
+/// How `genpass --output-format` prints the generated passwords.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordFormat {
+    Json,
+    Csv,
+}
+
+impl From<PasswordFormat> for &'static str {
+    fn from(format: PasswordFormat) -> Self {
+        match format {
+            PasswordFormat::Json => "json",
+            PasswordFormat::Csv => "csv",
+        }
+    }
+}
+
+impl FromStr for PasswordFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "json" => Ok(PasswordFormat::Json),
+            "csv" => Ok(PasswordFormat::Csv),
+            _ => anyhow::bail!("Invalid output format: {}", s),
+        }
+    }
+}
+
+impl fmt::Display for PasswordFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
+    }
+}
+
+impl TryFrom<String> for CharClass {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for CharClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", Into::<&str>::into(*self))
     }
 }
@@ -1,12 +1,17 @@
 mod base64;
+mod convert;
 mod csv;
 mod genpass;
 mod http;
+mod password;
+mod query;
 mod text;
 
 use std::path::{Path, PathBuf};
 
-pub use self::{base64::*, csv::*, genpass::*, http::*, text::*};
+pub use self::{
+    base64::*, convert::*, csv::*, genpass::*, http::*, password::*, query::*, text::*,
+};
 use clap::Parser;
 use enum_dispatch::enum_dispatch;
 
@@ -17,13 +22,27 @@ pub struct Opts {
     pub cmd: SubCommand,
 }
 
+// 命令行参数只解析一次，不必为了变体大小而装箱
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Parser)]
 #[enum_dispatch(CmdExecutor)]
 pub enum SubCommand {
     #[command(name = "csv", about = "Show csv , or convert csv to other format")]
     Csv(CsvOpts),
+    #[command(
+        name = "convert",
+        about = "Convert between json, ndjson, yaml, toml and csv"
+    )]
+    Convert(ConvertOpts),
+    #[command(
+        name = "query",
+        about = "Query json, ndjson, yaml, toml or csv with a jq style filter"
+    )]
+    Query(QueryOpts),
     #[command(name = "genpass", about = "Generate random password")]
     Genpass(GenPassOpts),
+    #[command(subcommand, about = "Check password strength")]
+    Password(PasswordSubCommand),
     #[command(subcommand, about = "Base64 encode or decode")]
     Base64(Base64SubCommand),
     #[command(subcommand, about = "Text sign or verify")]
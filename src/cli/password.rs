@@ -0,0 +1,57 @@
+use clap::Parser;
+use enum_dispatch::enum_dispatch;
+use std::io::Write;
+
+use crate::{get_writer, process_password_check, render_password_report, CmdExecutor};
+
+use super::{verify_file, ReportFormat};
+
+#[derive(Debug, Parser)]
+#[enum_dispatch(CmdExecutor)]
+pub enum PasswordSubCommand {
+    #[command(about = "Report zxcvbn strength of passwords, one per line")]
+    Check(PasswordCheckOpts),
+}
+
+#[derive(Debug, Parser)]
+pub struct PasswordCheckOpts {
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+
+    /// Words an attacker may guess first, e.g. `--user-inputs alice,acme`
+    #[arg(long, value_delimiter = ',')]
+    pub user_inputs: Vec<String>,
+
+    /// Exit with status 1 when any password scores below this (0-4)
+    #[arg(long, value_parser = clap::value_parser!(u8).range(0..=4))]
+    pub min_score: Option<u8>,
+
+    /// Report format: table or json
+    #[arg(long, default_value = "table")]
+    pub format: ReportFormat,
+}
+
+impl CmdExecutor for PasswordCheckOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let reports = process_password_check(&self.input, &self.user_inputs)?;
+        let mut output = get_writer("-")?;
+        match self.format {
+            ReportFormat::Table => write!(output, "{}", render_password_report(&reports))?,
+            ReportFormat::Json => writeln!(output, "{}", serde_json::to_string_pretty(&reports)?)?,
+        }
+        output.finish()?;
+        if let Some(min) = self.min_score {
+            let weak = reports.iter().filter(|r| r.score < min).count();
+            if weak > 0 {
+                eprintln!(
+                    "{} of {} passwords scored below {}",
+                    weak,
+                    reports.len(),
+                    min
+                );
+                std::process::exit(1);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,57 @@
+use std::io::Write;
+
+use clap::Parser;
+use serde_json::Value;
+
+use crate::{get_writer, process_query, write_document, CmdExecutor};
+
+use super::{verify_file, OutputFormat};
+
+#[derive(Debug, Parser)]
+pub struct QueryOpts {
+    /// Filter to run, e.g. `.players[] | select(.age > 30) | .name`
+    pub filter: String,
+
+    #[arg(short, long, value_parser = verify_file, default_value = "-")]
+    pub input: String,
+
+    /// Output file, `-` writes to stdout
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
+
+    /// Input format, detected from the file extension or content if omitted
+    #[arg(long)]
+    pub from: Option<OutputFormat>,
+
+    /// Write the results in this format, several results are written as one array
+    #[arg(long)]
+    pub to: Option<OutputFormat>,
+
+    /// Print strings without quotes, one result per line
+    #[arg(short, long, conflicts_with = "to")]
+    pub raw: bool,
+}
+
+impl CmdExecutor for QueryOpts {
+    async fn execute(self) -> anyhow::Result<()> {
+        let mut results = process_query(&self.input, &self.filter, self.from)?;
+        let mut writer = get_writer(&self.output)?;
+        if let Some(to) = self.to {
+            let document = if results.len() == 1 {
+                results.remove(0)
+            } else {
+                Value::Array(results)
+            };
+            write_document(&document, to, &mut writer)?;
+            return writer.finish();
+        }
+        for result in results {
+            match result {
+                Value::String(s) if self.raw => writeln!(writer, "{}", s)?,
+                _ if self.raw => writeln!(writer, "{}", serde_json::to_string(&result)?)?,
+                _ => writeln!(writer, "{}", serde_json::to_string_pretty(&result)?)?,
+            }
+        }
+        writer.finish()
+    }
+}
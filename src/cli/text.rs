@@ -1,8 +1,10 @@
 use clap::Parser;
 use enum_dispatch::enum_dispatch;
-use std::{fmt, fs, path::PathBuf, str::FromStr};
+use std::{fmt, fs, io::Write, path::PathBuf, str::FromStr};
 
-use crate::{process_text_generate, process_text_sign, process_text_verify, CmdExecutor};
+use crate::{
+    get_writer, process_text_generate, process_text_sign, process_text_verify, CmdExecutor,
+};
 
 use super::{verify_file, verify_path};
 
@@ -20,8 +22,12 @@ pub enum TextSubCommand {
 
 #[derive(Debug, Parser)]
 pub struct TextSignOpts {
+    /// Message to sign, `-` reads stdin; `.gz`, `.zst` and `.bz2` files are decompressed first
     #[arg(short, long, value_parser = verify_file, default_value = "-")]
     pub input: String,
+    /// Output file for the signature, `-` writes to stdout
+    #[arg(short, long, default_value = "-")]
+    pub output: String,
 
     #[arg(short, long, value_parser = verify_file)]
     pub key: String,
@@ -31,6 +37,7 @@ pub struct TextSignOpts {
 
 #[derive(Debug, Parser)]
 pub struct TextVerifyOpts {
+    /// Message to verify, `-` reads stdin; `.gz`, `.zst` and `.bz2` files are decompressed first
     #[arg(short, long, value_parser = verify_file, default_value = "-")]
     pub input: String,
     #[arg(short, long, value_parser = verify_file)]
@@ -90,8 +97,9 @@ impl fmt::Display for TextSignFormat {
 impl CmdExecutor for TextSignOpts {
     async fn execute(self) -> anyhow::Result<()> {
         let signed = process_text_sign(&self.input, &self.key, self.format)?;
-        println!("{}", signed);
-        Ok(())
+        let mut writer = get_writer(&self.output)?;
+        writeln!(writer, "{}", signed)?;
+        writer.finish()
     }
 }
 
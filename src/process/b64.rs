@@ -36,12 +36,12 @@ pub fn process_decode(reader: &mut Box<dyn Read>, format: Base64Format) -> anyho
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::get_reader;
+    use crate::get_input_reader;
 
     #[test]
     fn test_process_encode() -> anyhow::Result<()> {
         let input = "Cargo.toml";
-        let mut reader = get_reader(input)?;
+        let mut reader = get_input_reader(input)?;
         let format = Base64Format::Standard;
         assert!(process_encode(&mut reader, format).is_ok());
         Ok(())
@@ -50,7 +50,7 @@ mod tests {
     #[test]
     fn test_process_decode() -> anyhow::Result<()> {
         let input = "fixtures/b64.txt";
-        let mut reader = get_reader(input)?;
+        let mut reader = get_input_reader(input)?;
         let format = Base64Format::UrlSafe;
         assert!(process_decode(&mut reader, format).is_ok());
         Ok(())
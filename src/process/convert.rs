@@ -0,0 +1,294 @@
+use crate::{
+    cli::{CsvReadOpts, CsvTransformOpts, OutputFormat},
+    get_data_reader, get_writer, read_records, record_writer, CsvRecords,
+};
+use anyhow::Context;
+use serde::Deserialize;
+use serde_json::Value;
+use std::{
+    io::{Cursor, Read, Write},
+    path::Path,
+};
+
+use super::format::unwrap_records;
+
+/// Convert a whole document between JSON, NDJSON, YAML, TOML, CSV, CBOR and MessagePack.
+pub fn process_convert(
+    input: &str,
+    output: &str,
+    from: Option<OutputFormat>,
+    to: Option<OutputFormat>,
+) -> anyhow::Result<()> {
+    let to = to.or_else(|| detect_path_format(output)).with_context(|| {
+        format!(
+            "cannot tell the output format from {}, use --to to choose one",
+            output
+        )
+    })?;
+    let mut content = Vec::new();
+    get_data_reader(input)?.read_to_end(&mut content)?;
+    let from = match from.or_else(|| detect_path_format(input)) {
+        Some(from) => from,
+        None => detect_content_format(&content)?,
+    };
+
+    let document =
+        read_document(content, from).with_context(|| format!("{} is not valid {}", input, from))?;
+    let mut writer = get_writer(output)?;
+    write_document(&document, to, &mut writer)?;
+    writer.finish()
+}
+
+/// Pick the format from a file extension, looking past compression suffixes like `.gz`.
+pub fn detect_path_format(path: &str) -> Option<OutputFormat> {
+    let name = Path::new(path).file_name()?.to_str()?;
+    let (_, extensions) = name.split_once('.')?;
+    extensions
+        .rsplit('.')
+        .find(|ext| !matches!(*ext, "gz" | "gzip" | "zst" | "zstd" | "bz2"))?
+        .parse()
+        .ok()
+}
+
+/// Guess the format of a document from its content.
+pub fn detect_content_format(content: &[u8]) -> anyhow::Result<OutputFormat> {
+    let text = std::str::from_utf8(content)
+        .context("input is not UTF-8 text, use --from to read CBOR or MessagePack")?;
+    let trimmed = text.trim_start();
+    if trimmed.is_empty() {
+        anyhow::bail!("input is empty, use --from to choose a format");
+    }
+    if trimmed.starts_with(['{', '[']) {
+        if serde_json::from_str::<Value>(text).is_ok() {
+            return Ok(OutputFormat::Json);
+        }
+        let mut lines = text.lines().filter(|l| !l.trim().is_empty());
+        if lines.all(|l| serde_json::from_str::<Value>(l).is_ok()) {
+            return Ok(OutputFormat::Ndjson);
+        }
+    }
+    if toml::from_str::<toml::Table>(text).is_ok() {
+        return Ok(OutputFormat::Toml);
+    }
+    // 普通的 CSV 也能被解析为 YAML 字符串，所以只认映射和序列
+    let yaml = serde_yaml::Deserializer::from_str(text)
+        .next()
+        .and_then(|document| Value::deserialize(document).ok());
+    if trimmed.starts_with("---") || yaml.is_some_and(|v| v.is_object() || v.is_array()) {
+        return Ok(OutputFormat::Yaml);
+    }
+    Ok(OutputFormat::Csv)
+}
+
+/// Parse a document, NDJSON and CSV become an array of their records.
+pub fn read_document(content: Vec<u8>, format: OutputFormat) -> anyhow::Result<Value> {
+    Ok(match format {
+        OutputFormat::Json => serde_json::from_slice(&content)?,
+        OutputFormat::Ndjson => {
+            Value::Array(read_records(content.as_slice(), OutputFormat::Ndjson)?)
+        }
+        OutputFormat::Yaml => {
+            // 多文档的 YAML 转为数组
+            let documents = serde_yaml::Deserializer::from_slice(&content)
+                .map(Value::deserialize)
+                .collect::<Result<Vec<_>, _>>()?;
+            join_documents(documents)
+        }
+        OutputFormat::Toml => serde_json::to_value(toml::from_str::<toml::Table>(
+            std::str::from_utf8(&content)?,
+        )?)?,
+        OutputFormat::Csv => {
+            let records = CsvRecords::open(
+                Cursor::new(content),
+                &CsvReadOpts::default(),
+                &CsvTransformOpts::default(),
+            )?;
+            Value::Array(
+                records
+                    .map(|r| r.map(Value::Object))
+                    .collect::<anyhow::Result<_>>()?,
+            )
+        }
+        // 二进制格式可能是逐条写出的记录序列，与多文档的 YAML 一样转为数组
+        OutputFormat::Cbor => {
+            let mut reader = content.as_slice();
+            let mut documents = Vec::new();
+            while !reader.is_empty() {
+                documents.push(ciborium::from_reader(&mut reader)?);
+            }
+            join_documents(documents)
+        }
+        OutputFormat::Msgpack => {
+            let mut reader = content.as_slice();
+            let mut documents = Vec::new();
+            while !reader.is_empty() {
+                documents.push(rmp_serde::from_read(&mut reader)?);
+            }
+            join_documents(documents)
+        }
+        other => anyhow::bail!("{} can only be written, not read", other),
+    })
+}
+
+fn join_documents(mut documents: Vec<Value>) -> Value {
+    match documents.len() {
+        0 => Value::Null,
+        1 => documents.remove(0),
+        _ => Value::Array(documents),
+    }
+}
+
+/// Write a document, failing before any output if the format can't represent it.
+pub fn write_document(
+    document: &Value,
+    format: OutputFormat,
+    mut writer: impl Write,
+) -> anyhow::Result<()> {
+    check_representable(document, format)?;
+    match format {
+        OutputFormat::Json => {
+            serde_json::to_writer_pretty(&mut writer, document)?;
+            writer.write_all(b"\n")?;
+        }
+        OutputFormat::Ndjson => {
+            let items = match document {
+                Value::Array(items) => items.as_slice(),
+                _ => std::slice::from_ref(document),
+            };
+            for item in items {
+                serde_json::to_writer(&mut writer, item)?;
+                writer.write_all(b"\n")?;
+            }
+        }
+        OutputFormat::Yaml => serde_yaml::to_writer(&mut writer, document)?,
+        OutputFormat::Toml => writer.write_all(toml::to_string_pretty(document)?.as_bytes())?,
+        // CSV、表格和二进制格式按记录写出
+        other => {
+            let mut records = record_writer(other, &mut writer);
+            for record in unwrap_records(document.clone()) {
+                records.write_record(&record)?;
+            }
+            records.finish()?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Explain up front what the target format can't hold, rather than failing halfway through.
+fn check_representable(document: &Value, format: OutputFormat) -> anyhow::Result<()> {
+    if format != OutputFormat::Toml {
+        return Ok(());
+    }
+    match document {
+        Value::Object(_) => {}
+        Value::Array(items) => anyhow::bail!(
+            "TOML needs a table at the top level, but the input is an array of {} items; \
+             put the array under a key or convert to another format",
+            items.len()
+        ),
+        _ => anyhow::bail!("TOML needs a table at the top level, but the input is a single value"),
+    }
+    if let Some(path) = find_null(document, String::new()) {
+        anyhow::bail!("TOML has no null value, but the input has null at {}", path);
+    }
+    Ok(())
+}
+
+/// 返回第一个 null 的路径，例如 `.players[1].age`
+fn find_null(value: &Value, path: String) -> Option<String> {
+    match value {
+        Value::Null => Some(if path.is_empty() { ".".into() } else { path }),
+        Value::Object(map) => map
+            .iter()
+            .find_map(|(k, v)| find_null(v, format!("{}.{}", path, k))),
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .find_map(|(i, v)| find_null(v, format!("{}[{}]", path, i))),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn convert(content: &str, from: OutputFormat, to: OutputFormat) -> anyhow::Result<String> {
+        let document = read_document(content.as_bytes().to_vec(), from)?;
+        let mut output = Vec::new();
+        write_document(&document, to, &mut output)?;
+        Ok(String::from_utf8(output)?)
+    }
+
+    #[test]
+    fn test_convert_yaml_to_toml_and_back() -> anyhow::Result<()> {
+        let yaml = std::fs::read_to_string("fixtures/team.yaml")?;
+        let toml = convert(&yaml, OutputFormat::Yaml, OutputFormat::Toml)?;
+        assert!(toml.starts_with("name = \"Juventus\"\nfounded = 1897\n"));
+        assert!(toml.contains("[[players]]\nname = \"Gianluigi Buffon\""));
+        assert_eq!(
+            convert(&toml, OutputFormat::Toml, OutputFormat::Yaml)?,
+            yaml
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_convert_to_csv_and_ndjson() -> anyhow::Result<()> {
+        let json = r#"{"players": [{"name": "Buffon", "age": 41}, {"name": "Dybala", "age": 25}]}"#;
+        let csv = convert(json, OutputFormat::Json, OutputFormat::Csv)?;
+        assert_eq!(csv, "name,age\nBuffon,41\nDybala,25\n");
+        let ndjson = convert(&csv, OutputFormat::Csv, OutputFormat::Ndjson)?;
+        assert_eq!(
+            ndjson,
+            "{\"name\":\"Buffon\",\"age\":41}\n{\"name\":\"Dybala\",\"age\":25}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_convert_toml_errors() {
+        let err = convert("[1, 2]", OutputFormat::Json, OutputFormat::Toml)
+            .expect_err("arrays can't be TOML documents");
+        assert!(err.to_string().contains("array of 2 items"));
+        let err = convert(
+            r#"{"players": [{"age": null}]}"#,
+            OutputFormat::Json,
+            OutputFormat::Toml,
+        )
+        .expect_err("TOML has no null");
+        assert!(err.to_string().contains(".players[0].age"));
+    }
+
+    #[test]
+    fn test_read_binary_documents() -> anyhow::Result<()> {
+        let team = serde_json::json!({"name": "Juventus", "founded": 1897});
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&team, &mut cbor)?;
+        assert_eq!(read_document(cbor, OutputFormat::Cbor)?, team);
+        let mut msgpack = rmp_serde::to_vec_named(&team)?;
+        msgpack.extend(rmp_serde::to_vec_named(&team)?);
+        assert_eq!(
+            read_document(msgpack, OutputFormat::Msgpack)?,
+            Value::Array(vec![team.clone(), team])
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_detect_format() -> anyhow::Result<()> {
+        assert_eq!(detect_path_format("a/team.yml"), Some(OutputFormat::Yaml));
+        assert_eq!(detect_path_format("team.json.gz"), Some(OutputFormat::Json));
+        assert_eq!(detect_path_format("-"), None);
+        assert_eq!("yml".parse::<OutputFormat>()?, OutputFormat::Yaml);
+        assert_eq!("jsonl".parse::<OutputFormat>()?, OutputFormat::Ndjson);
+        let detect = |s: &str| detect_content_format(s.as_bytes());
+        assert_eq!(detect("{\"a\": 1}")?, OutputFormat::Json);
+        assert_eq!(detect("{\"a\": 1}\n{\"a\": 2}\n")?, OutputFormat::Ndjson);
+        assert_eq!(detect("[team]\nname = \"Juventus\"\n")?, OutputFormat::Toml);
+        assert_eq!(detect("name: Juventus\n")?, OutputFormat::Yaml);
+        assert_eq!(detect("name,age\nBuffon,41\n")?, OutputFormat::Csv);
+        Ok(())
+    }
+}
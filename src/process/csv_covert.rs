@@ -1,36 +1,791 @@
-use crate::cli::OutputFormat;
-use csv::Reader;
-use std::fs;
-
-pub fn process_csv(input: &str, output: String, format: OutputFormat) -> anyhow::Result<()> {
-    let mut reader = Reader::from_path(input)?;
-    let headers = reader.headers()?.clone();
-    let mut records = Vec::with_capacity(128);
-
-    // 将 CSV 记录转换为更适合序列化的格式
-    for result in reader.records() {
-        let record = result?;
-        let mut map = serde_json::Map::new();
-        for (header, value) in headers.iter().zip(record.iter()) {
-            map.insert(
-                header.to_string(),
-                serde_json::Value::String(value.to_string()),
-            );
+use super::csv_filter::{resolve_column, Filter};
+use crate::{
+    cli::{ColumnSpec, ColumnType, CsvReadOpts, CsvTransformOpts, OutputFormat},
+    decode_reader, record_writer,
+};
+use anyhow::Context;
+use csv::{Reader, ReaderBuilder, StringRecord, StringRecordsIntoIter};
+use serde_json::{Map, Value};
+use std::{
+    collections::VecDeque,
+    io::{Read, Write},
+};
+
+/// 类型推断使用的样本行数，之后的记录不再缓存，逐条转换并写出
+const INFER_SAMPLE_ROWS: usize = 1000;
+
+/// How the cells of a column are turned into JSON values.
+#[derive(Debug, Clone, Copy)]
+enum CellType {
+    /// Keep the cell verbatim as a string.
+    Raw,
+    /// Inferred from the sample rows, later cells that don't fit are kept as strings.
+    Inferred(ColumnType),
+    /// Given by `--types`, cells that don't fit are an error.
+    Explicit(ColumnType),
+}
+
+/// Turns raw CSV records into typed JSON objects keyed by column name.
+#[derive(Debug)]
+pub struct RecordConverter {
+    headers: Vec<String>,
+    cell_types: Vec<CellType>,
+}
+
+/// Typed, filtered and projected records read from a CSV file, one at a time.
+pub struct CsvRecords {
+    /// 类型推断时缓存的样本行，迭代时先返回它们
+    sample: VecDeque<StringRecord>,
+    rows: StringRecordsIntoIter<Box<dyn Read>>,
+    converter: RecordConverter,
+    filter: Option<Filter>,
+    /// 输出列名与对应的源列名
+    projection: Vec<(String, String)>,
+    /// 使用 --unflatten 时每个输出列对应的嵌套路径
+    paths: Option<Vec<Vec<PathSegment>>>,
+}
+
+/// One step of a header path such as `address.city` or `tags[0]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+/// Convert CSV from `reader` into `format`, streaming the records to `writer`.
+pub fn process_csv(
+    reader: impl Read + 'static,
+    writer: impl Write,
+    format: OutputFormat,
+    read: &CsvReadOpts,
+    transform: &CsvTransformOpts,
+) -> anyhow::Result<()> {
+    let records = CsvRecords::open(reader, read, transform)?;
+    let mut writer = record_writer(format, writer);
+    for record in records {
+        let mut record = record?;
+        // TOML 无法表示 null，直接省略该字段
+        if matches!(format, OutputFormat::Toml) {
+            strip_nulls(&mut record)?;
+        }
+        writer.write_record(&Value::Object(record))?;
+    }
+    writer.finish()?;
+
+    Ok(())
+}
+
+impl CsvRecords {
+    pub fn open(
+        reader: impl Read + 'static,
+        read: &CsvReadOpts,
+        transform: &CsvTransformOpts,
+    ) -> anyhow::Result<Self> {
+        let (headers, reader) = csv_reader(reader, read)?;
+        let mut rows = reader.into_records();
+        let sample = rows
+            .by_ref()
+            .take(INFER_SAMPLE_ROWS)
+            .collect::<Result<Vec<_>, _>>()?;
+        let converter =
+            RecordConverter::new(&headers, &sample, !transform.no_infer, &transform.types)?;
+        let filter = match &transform.filter {
+            Some(filter) => Some(Filter::parse(filter)?.bind(headers.iter())?),
+            None => None,
+        };
+        let projection = projection(&headers, &transform.select, &transform.rename)?
+            .into_iter()
+            .map(|(source, target)| (target.to_string(), source.to_string()))
+            .collect::<Vec<_>>();
+        let paths = if transform.unflatten {
+            let paths = projection
+                .iter()
+                .map(|(target, _)| parse_path(target, projection.len()))
+                .collect::<anyhow::Result<_>>()?;
+            Some(paths)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            sample: sample.into(),
+            rows,
+            converter,
+            filter,
+            projection,
+            paths,
+        })
+    }
+
+    /// The names of the output columns, in order.
+    pub fn headers(&self) -> impl Iterator<Item = &str> {
+        self.projection.iter().map(|(target, _)| target.as_str())
+    }
+
+    fn next_row(&mut self) -> Option<csv::Result<StringRecord>> {
+        self.sample.pop_front().map(Ok).or_else(|| self.rows.next())
+    }
+}
+
+impl Iterator for CsvRecords {
+    type Item = anyhow::Result<Map<String, Value>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let row = match self.next_row()? {
+                Ok(row) => row,
+                Err(e) => return Some(Err(e.into())),
+            };
+            let mut record = match self.converter.convert(&row) {
+                Ok(record) => record,
+                Err(e) => return Some(Err(e)),
+            };
+            if self.filter.as_ref().is_some_and(|f| !f.matches(&record)) {
+                continue;
+            }
+            let projected = self.projection.iter().map(|(target, source)| {
+                let value = record.remove(source).unwrap_or(Value::Null);
+                (target.clone(), value)
+            });
+            return Some(match &self.paths {
+                Some(paths) => unflatten(projected, paths),
+                None => Ok(projected.collect()),
+            });
         }
-        records.push(serde_json::Value::Object(map));
     }
+}
 
-    // 根据不同格式进行序列化
-    let content = match format {
-        OutputFormat::Json => serde_json::to_string_pretty(&records)?,
-        OutputFormat::Yaml => serde_yaml::to_string(&records)?,
-        OutputFormat::Toml => {
-            // 将记录包装在一个对象中，使其符合 TOML 格式要求
-            let wrapper = serde_json::json!({ "records": records });
-            toml::to_string_pretty(&wrapper)?
+/// Parse a header like `address.city` or `tags[0].name` into its path segments.
+///
+/// Array indices must be below `max_index`, normally the number of columns: a file with fewer
+/// columns can't fill a longer array, and the index would otherwise size the allocation.
+pub fn parse_path(header: &str, max_index: usize) -> anyhow::Result<Vec<PathSegment>> {
+    let mut segments = Vec::new();
+    let mut rest = header;
+    while !rest.is_empty() {
+        if let Some(after) = rest.strip_prefix('[') {
+            let (index, after) = after
+                .split_once(']')
+                .with_context(|| format!("missing ] in column path {}", header))?;
+            let index: usize = index
+                .parse()
+                .with_context(|| format!("invalid array index in column path {}", header))?;
+            anyhow::ensure!(
+                index < max_index,
+                "array index {} in column path {} is out of range, the file has {} columns",
+                index,
+                header,
+                max_index
+            );
+            segments.push(PathSegment::Index(index));
+            rest = after.strip_prefix('.').unwrap_or(after);
+        } else {
+            let end = rest.find(['.', '[']).unwrap_or(rest.len());
+            anyhow::ensure!(end > 0, "empty key in column path {}", header);
+            segments.push(PathSegment::Key(rest[..end].to_string()));
+            rest = &rest[end..];
+            rest = rest.strip_prefix('.').unwrap_or(rest);
         }
+    }
+    anyhow::ensure!(!segments.is_empty(), "empty column path");
+    Ok(segments)
+}
+
+/// Build nested objects and arrays from flat cells whose header paths were parsed by `parse_path`.
+fn unflatten(
+    cells: impl Iterator<Item = (String, Value)>,
+    paths: &[Vec<PathSegment>],
+) -> anyhow::Result<Map<String, Value>> {
+    let mut root = Value::Object(Map::new());
+    for ((header, value), path) in cells.zip(paths) {
+        insert_path(&mut root, path, value)
+            .with_context(|| format!("column {} conflicts with another column path", header))?;
+    }
+    match root {
+        Value::Object(map) => Ok(map),
+        _ => unreachable!("root is always an object"),
+    }
+}
+
+fn insert_path(slot: &mut Value, path: &[PathSegment], value: Value) -> anyhow::Result<()> {
+    let Some((first, rest)) = path.split_first() else {
+        anyhow::ensure!(slot.is_null(), "value already set");
+        *slot = value;
+        return Ok(());
     };
+    match first {
+        PathSegment::Key(key) => {
+            if slot.is_null() {
+                *slot = Value::Object(Map::new());
+            }
+            let Value::Object(map) = slot else {
+                anyhow::bail!("{} is not an object", key);
+            };
+            insert_path(map.entry(key.clone()).or_insert(Value::Null), rest, value)
+        }
+        PathSegment::Index(index) => {
+            if slot.is_null() {
+                *slot = Value::Array(Vec::new());
+            }
+            let Value::Array(items) = slot else {
+                anyhow::bail!("[{}] is not an array", index);
+            };
+            if items.len() <= *index {
+                items.resize(index + 1, Value::Null);
+            }
+            insert_path(&mut items[*index], rest, value)
+        }
+    }
+}
 
-    fs::write(output, content)?;
+/// Remove `null` members from objects and trailing `null` items from arrays, recursively.
+///
+/// A `null` followed by other items is an error: dropping it would shift the later indices.
+fn strip_nulls(map: &mut Map<String, Value>) -> anyhow::Result<()> {
+    map.retain(|_, v| !v.is_null());
+    for (key, value) in map.iter_mut() {
+        strip_nested_nulls(value).with_context(|| format!("cannot write {} as TOML", key))?;
+    }
     Ok(())
 }
+
+fn strip_nested_nulls(value: &mut Value) -> anyhow::Result<()> {
+    match value {
+        Value::Object(map) => strip_nulls(map)?,
+        Value::Array(items) => {
+            while items.last().is_some_and(Value::is_null) {
+                items.pop();
+            }
+            if let Some(index) = items.iter().position(Value::is_null) {
+                anyhow::bail!(
+                    "TOML has no null, and item [{}] is empty while later items are not",
+                    index
+                );
+            }
+            items.iter_mut().try_for_each(strip_nested_nulls)?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Pair each output column with its source column, applying `--select` and `--rename`.
+fn projection<'a>(
+    headers: &'a StringRecord,
+    select: &'a [String],
+    rename: &'a [(String, String)],
+) -> anyhow::Result<Vec<(&'a str, &'a str)>> {
+    let columns = if select.is_empty() {
+        headers.iter().collect()
+    } else {
+        select
+            .iter()
+            .map(|name| resolve_column(name, headers.iter()))
+            .collect::<anyhow::Result<Vec<_>>>()?
+    };
+    let rename = rename
+        .iter()
+        .map(|(from, to)| Ok((resolve_column(from, headers.iter())?, to.as_str())))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    Ok(columns
+        .into_iter()
+        .map(|column| {
+            let target = rename
+                .iter()
+                .find(|(from, _)| *from == column)
+                .map_or(column, |(_, to)| *to);
+            (column, target)
+        })
+        .collect())
+}
+
+impl RecordConverter {
+    /// Resolve how every column is converted, inferring from the sample rows where needed.
+    pub fn new(
+        headers: &StringRecord,
+        sample: &[StringRecord],
+        infer: bool,
+        types: &[ColumnSpec],
+    ) -> anyhow::Result<Self> {
+        if let Some(spec) = types.iter().find(|s| !headers.iter().any(|h| h == s.name)) {
+            anyhow::bail!("Unknown column in --types: {}", spec.name);
+        }
+
+        let cell_types = headers
+            .iter()
+            .enumerate()
+            .map(|(i, header)| {
+                if let Some(spec) = types.iter().find(|s| s.name == header) {
+                    CellType::Explicit(spec.ty)
+                } else if infer {
+                    CellType::Inferred(infer_column_type(sample.iter().filter_map(|r| r.get(i))))
+                } else {
+                    CellType::Raw
+                }
+            })
+            .collect();
+        Ok(Self {
+            headers: headers.iter().map(String::from).collect(),
+            cell_types,
+        })
+    }
+
+    pub fn convert(&self, record: &StringRecord) -> anyhow::Result<Map<String, Value>> {
+        let mut map = Map::with_capacity(self.headers.len());
+        for ((header, value), ty) in self.headers.iter().zip(record.iter()).zip(&self.cell_types) {
+            let value = match ty {
+                CellType::Raw => Value::String(value.to_string()),
+                // 推断只看了前面的样本行，之后不符合推断类型的值按字符串保留
+                CellType::Inferred(ty) => convert_inferred(value, *ty),
+                CellType::Explicit(ty) => convert_cell(value, *ty).with_context(|| {
+                    let line = record.position().map_or(0, |p| p.line());
+                    format!("line {}: invalid value for column {}", line, header)
+                })?,
+            };
+            map.insert(header.clone(), value);
+        }
+        Ok(map)
+    }
+}
+
+/// Read CSV with the given delimiter and header options, returning the column names
+/// alongside a reader positioned at the first data record.
+pub fn csv_reader(
+    reader: impl Read + 'static,
+    opts: &CsvReadOpts,
+) -> anyhow::Result<(StringRecord, Reader<Box<dyn Read>>)> {
+    let reader = decode_reader(reader, opts.encoding)?;
+    let mut reader = ReaderBuilder::new()
+        .delimiter(opts.delimiter as u8)
+        .has_headers(opts.header)
+        .from_reader(reader);
+    // 没有表头时 headers() 返回第一条记录（不会被消费），用来确定列数
+    let first = reader.headers()?.clone();
+
+    let headers = if !opts.columns.is_empty() {
+        anyhow::ensure!(
+            opts.columns.len() == first.len(),
+            "--columns has {} names but the file has {} columns",
+            opts.columns.len(),
+            first.len()
+        );
+        StringRecord::from(opts.columns.clone())
+    } else if opts.header {
+        first
+    } else {
+        (1..=first.len()).map(|i| format!("col{}", i)).collect()
+    };
+    Ok((headers, reader))
+}
+
+/// Infer the narrowest type that every non-empty value in a column fits into.
+pub fn infer_column_type<'a>(values: impl Iterator<Item = &'a str>) -> ColumnType {
+    let mut guess = TypeGuess::default();
+    for value in values {
+        guess.observe(value);
+        if guess.is_string() {
+            break;
+        }
+    }
+    guess.column_type()
+}
+
+/// Incremental column type inference, fed one cell at a time.
+#[derive(Debug, Clone)]
+pub struct TypeGuess {
+    seen: bool,
+    int: bool,
+    float: bool,
+    boolean: bool,
+}
+
+impl Default for TypeGuess {
+    fn default() -> Self {
+        Self {
+            seen: false,
+            int: true,
+            float: true,
+            boolean: true,
+        }
+    }
+}
+
+impl TypeGuess {
+    pub fn observe(&mut self, value: &str) {
+        if value.is_empty() {
+            return;
+        }
+        self.seen = true;
+        self.int = self.int && is_int(value);
+        self.float = self.float && is_float(value);
+        self.boolean = self.boolean && parse_bool(value).is_some();
+    }
+
+    /// Whether the column can no longer be anything but a string.
+    pub fn is_string(&self) -> bool {
+        self.seen && !self.int && !self.float && !self.boolean
+    }
+
+    pub fn column_type(&self) -> ColumnType {
+        if !self.seen {
+            ColumnType::String
+        } else if self.int {
+            ColumnType::Int
+        } else if self.float {
+            ColumnType::Float
+        } else if self.boolean {
+            ColumnType::Bool
+        } else {
+            ColumnType::String
+        }
+    }
+}
+
+/// Convert a single cell to a JSON value of the given type. Empty cells become `null`.
+pub fn convert_cell(value: &str, ty: ColumnType) -> anyhow::Result<Value> {
+    if value.is_empty() {
+        return Ok(Value::Null);
+    }
+
+    Ok(match ty {
+        ColumnType::String => Value::String(value.to_string()),
+        ColumnType::Int => Value::from(value.parse::<i64>()?),
+        ColumnType::Float => {
+            let n = serde_json::Number::from_f64(value.parse::<f64>()?)
+                .with_context(|| format!("{} is not a finite number", value))?;
+            Value::Number(n)
+        }
+        ColumnType::Bool => {
+            Value::Bool(parse_bool(value).with_context(|| format!("{} is not a boolean", value))?)
+        }
+        ColumnType::Date => {
+            anyhow::ensure!(is_date(value), "{} is not a YYYY-MM-DD date", value);
+            Value::String(value.to_string())
+        }
+    })
+}
+
+/// Convert a cell of an inferred column, applying the same checks as inference
+/// and keeping the cell as a string when it doesn't fit.
+fn convert_inferred(value: &str, ty: ColumnType) -> Value {
+    let fits = match ty {
+        ColumnType::Int => value.is_empty() || is_int(value),
+        ColumnType::Float => value.is_empty() || is_float(value),
+        _ => true,
+    };
+    match fits.then(|| convert_cell(value, ty)) {
+        Some(Ok(v)) => v,
+        _ => Value::String(value.to_string()),
+    }
+}
+
+fn is_int(value: &str) -> bool {
+    !has_leading_zero(value) && value.parse::<i64>().is_ok()
+}
+
+fn is_float(value: &str) -> bool {
+    // 排除 "inf"、"NaN" 之类 f64 能解析但 JSON 无法表示的值
+    !has_leading_zero(value)
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_digit() || b"+-.eE".contains(&b))
+        && value.bytes().any(|b| b.is_ascii_digit())
+        && value.parse::<f64>().is_ok()
+}
+
+/// 保留前导零（如邮编 "007"）的值按字符串处理
+fn has_leading_zero(value: &str) -> bool {
+    let digits = value.strip_prefix(['-', '+']).unwrap_or(value).as_bytes();
+    digits.len() > 1 && digits[0] == b'0' && digits[1].is_ascii_digit()
+}
+
+fn parse_bool(value: &str) -> Option<bool> {
+    if value.eq_ignore_ascii_case("true") {
+        Some(true)
+    } else if value.eq_ignore_ascii_case("false") {
+        Some(false)
+    } else {
+        None
+    }
+}
+
+/// Accept `YYYY-MM-DD`, optionally followed by a time part (`T...` or ` ...`).
+fn is_date(value: &str) -> bool {
+    let date = value.get(..10).unwrap_or(value);
+    let rest = &value[date.len()..];
+    let parts: Vec<&str> = date.split('-').collect();
+    if parts.len() != 3 || !(rest.is_empty() || rest.starts_with(['T', ' '])) {
+        return false;
+    }
+    let (Ok(year), Ok(month), Ok(day)) = (
+        parts[0].parse::<u16>(),
+        parts[1].parse::<u8>(),
+        parts[2].parse::<u8>(),
+    ) else {
+        return false;
+    };
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let days = match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return false,
+    };
+    parts[0].len() == 4 && (1..=days).contains(&day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs::File, io::Cursor};
+
+    #[test]
+    fn test_infer_column_type() {
+        assert_eq!(
+            infer_column_type(["1", "", "-3"].into_iter()),
+            ColumnType::Int
+        );
+        assert_eq!(
+            infer_column_type(["1", "2.5"].into_iter()),
+            ColumnType::Float
+        );
+        assert_eq!(
+            infer_column_type(["true", "FALSE"].into_iter()),
+            ColumnType::Bool
+        );
+        assert_eq!(
+            infer_column_type(["007", "1"].into_iter()),
+            ColumnType::String
+        );
+        assert_eq!(
+            infer_column_type(["1", "NaN"].into_iter()),
+            ColumnType::String
+        );
+        assert_eq!(infer_column_type(["", ""].into_iter()), ColumnType::String);
+    }
+
+    #[test]
+    fn test_convert_cell() -> anyhow::Result<()> {
+        assert_eq!(convert_cell("27", ColumnType::Int)?, Value::from(27));
+        assert_eq!(convert_cell("", ColumnType::Int)?, Value::Null);
+        assert_eq!(convert_cell("True", ColumnType::Bool)?, Value::Bool(true));
+        assert_eq!(convert_cell("2024-02-29", ColumnType::Date)?, "2024-02-29");
+        assert!(convert_cell("2023-02-29", ColumnType::Date).is_err());
+        assert!(convert_cell("abc", ColumnType::Float).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_infers_types() -> anyhow::Result<()> {
+        let mut output = Vec::new();
+        process_csv(
+            File::open("assets/juventus.csv")?,
+            &mut output,
+            OutputFormat::Json,
+            &CsvReadOpts::default(),
+            &CsvTransformOpts::default(),
+        )?;
+        let records: Vec<Value> = serde_json::from_slice(&output)?;
+        assert_eq!(records[0]["Kit Number"], Value::from(1));
+        assert_eq!(records[0]["Name"], "Wojciech Szczesny");
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_select_rename_where() -> anyhow::Result<()> {
+        let mut output = Vec::new();
+        let transform = CsvTransformOpts {
+            select: vec!["name".into(), "Kit Number".into()],
+            rename: vec![("Kit Number".into(), "number".into())],
+            filter: Some(r#"`Kit Number` > 30 && nationality == "Italy""#.into()),
+            ..Default::default()
+        };
+        process_csv(
+            File::open("assets/juventus.csv")?,
+            &mut output,
+            OutputFormat::Json,
+            &CsvReadOpts::default(),
+            &transform,
+        )?;
+        let records: Vec<Value> = serde_json::from_slice(&output)?;
+        assert_eq!(
+            records,
+            vec![
+                serde_json::json!({"Name": "Mattia Perin", "number": 37}),
+                serde_json::json!({"Name": "Gianluigi Buffon", "number": 77}),
+                serde_json::json!({"Name": "Carlo Pinsoglio", "number": 31}),
+                serde_json::json!({"Name": "Federico Bernardeschi", "number": 33}),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_streams_between_readers_and_writers() -> anyhow::Result<()> {
+        let input: &'static [u8] = b"name,age\nBuffon,41\nDybala,25\n";
+        let mut output = Vec::new();
+        process_csv(
+            input,
+            &mut output,
+            OutputFormat::Ndjson,
+            &CsvReadOpts::default(),
+            &CsvTransformOpts::default(),
+        )?;
+        assert_eq!(
+            String::from_utf8(output)?,
+            "{\"name\":\"Buffon\",\"age\":41}\n{\"name\":\"Dybala\",\"age\":25}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_keeps_cells_past_the_sample() -> anyhow::Result<()> {
+        let mut input = "age\n".to_string();
+        input.push_str(&"30\n".repeat(INFER_SAMPLE_ROWS));
+        input.push_str("007\nunknown\n");
+        let mut output = Vec::new();
+        process_csv(
+            Cursor::new(input),
+            &mut output,
+            OutputFormat::Ndjson,
+            &CsvReadOpts::default(),
+            &CsvTransformOpts::default(),
+        )?;
+        let output = String::from_utf8(output)?;
+        assert!(output.starts_with("{\"age\":30}\n"));
+        assert!(output.ends_with("{\"age\":\"007\"}\n{\"age\":\"unknown\"}\n"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_path() -> anyhow::Result<()> {
+        use PathSegment::*;
+        assert_eq!(
+            parse_path("address.city", 2)?,
+            vec![Key("address".into()), Key("city".into())]
+        );
+        assert_eq!(
+            parse_path("tags[1].name", 2)?,
+            vec![Key("tags".into()), Index(1), Key("name".into())]
+        );
+        assert!(parse_path("tags[x]", 2).is_err());
+        assert!(parse_path("a..b", 2).is_err());
+        assert!(parse_path("tags[2]", 2).is_err());
+        assert!(parse_path("tags[18446744073709551615]", 2).is_err());
+        assert!(parse_path("tags[4000000000]", 2).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_unflatten() -> anyhow::Result<()> {
+        let mut output = Vec::new();
+        let transform = CsvTransformOpts {
+            unflatten: true,
+            ..Default::default()
+        };
+        process_csv(
+            File::open("fixtures/nested.csv")?,
+            &mut output,
+            OutputFormat::Json,
+            &CsvReadOpts::default(),
+            &transform,
+        )?;
+        let records: Vec<Value> = serde_json::from_slice(&output)?;
+        assert_eq!(
+            records[0],
+            serde_json::json!({
+                "name": "Gianluigi Buffon",
+                "address": {"city": "Turin", "zip": 10121},
+                "tags": ["captain", "goalkeeper"]
+            })
+        );
+        assert_eq!(records[1]["tags"], serde_json::json!(["forward", null]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_unflatten_toml_nulls() -> anyhow::Result<()> {
+        let mut record = serde_json::json!({"a": null, "tags": ["x", null, null]});
+        let Value::Object(map) = &mut record else {
+            unreachable!()
+        };
+        strip_nulls(map)?;
+        assert_eq!(record, serde_json::json!({"tags": ["x"]}));
+
+        let mut record = serde_json::json!({"tags": [null, "y"]});
+        let Value::Object(map) = &mut record else {
+            unreachable!()
+        };
+        assert!(strip_nulls(map).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_unflatten_conflict() -> anyhow::Result<()> {
+        let paths = vec![parse_path("a", 2)?, parse_path("a.b", 2)?];
+        let cells = vec![
+            ("a".to_string(), Value::from(1)),
+            ("a.b".to_string(), Value::from(2)),
+        ];
+        assert!(unflatten(cells.into_iter(), &paths).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_csv_reader_tab_delimited() -> anyhow::Result<()> {
+        let opts = CsvReadOpts {
+            delimiter: '\t',
+            ..Default::default()
+        };
+        let (headers, mut reader) = csv_reader(File::open("fixtures/players.tsv")?, &opts)?;
+        assert_eq!(headers, vec!["name", "position", "age"]);
+        let record = reader.records().next().expect("has records")?;
+        assert_eq!(record, vec!["Buffon, Gianluigi", "Goalkeeper", "41"]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_csv_reader_semicolon_delimited() -> anyhow::Result<()> {
+        let opts = CsvReadOpts {
+            delimiter: ';',
+            ..Default::default()
+        };
+        let (headers, mut reader) =
+            csv_reader(File::open("fixtures/players_semicolon.csv")?, &opts)?;
+        assert_eq!(headers, vec!["name", "position", "age"]);
+        assert_eq!(reader.records().count(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_csv_reader_headerless() -> anyhow::Result<()> {
+        let opts = CsvReadOpts {
+            header: false,
+            ..Default::default()
+        };
+        let (headers, mut reader) =
+            csv_reader(File::open("fixtures/players_noheader.csv")?, &opts)?;
+        assert_eq!(headers, vec!["col1", "col2", "col3"]);
+        // 第一行是数据而不是表头
+        let record = reader.records().next().expect("has records")?;
+        assert_eq!(record, vec!["Gianluigi Buffon", "Goalkeeper", "41"]);
+        assert_eq!(reader.records().count(), 2);
+
+        let opts = CsvReadOpts {
+            header: false,
+            columns: vec!["name".into(), "position".into(), "age".into()],
+            ..Default::default()
+        };
+        let (headers, _) = csv_reader(File::open("fixtures/players_noheader.csv")?, &opts)?;
+        assert_eq!(headers, vec!["name", "position", "age"]);
+
+        let opts = CsvReadOpts {
+            columns: vec!["name".into()],
+            ..Default::default()
+        };
+        assert!(csv_reader(File::open("fixtures/players_noheader.csv")?, &opts).is_err());
+        Ok(())
+    }
+}
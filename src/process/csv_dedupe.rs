@@ -0,0 +1,69 @@
+use crate::{
+    cli::{CsvReadOpts, OutputFormat},
+    csv_reader, get_data_reader, get_writer, RowSink,
+};
+use std::collections::HashSet;
+
+use super::csv_filter::resolve_column;
+
+/// Drop rows whose key columns repeat an earlier row, keeping the first. With no key columns the
+/// whole row is the key.
+pub fn process_csv_dedupe(
+    input: &str,
+    keys: &[String],
+    opts: &CsvReadOpts,
+    output: &str,
+    format: Option<OutputFormat>,
+) -> anyhow::Result<()> {
+    let (headers, mut reader) = csv_reader(get_data_reader(input)?, opts)?;
+    let indexes = if keys.is_empty() {
+        (0..headers.len()).collect()
+    } else {
+        keys.iter()
+            .map(|key| {
+                let name = resolve_column(key, headers.iter())?;
+                Ok(headers.iter().position(|h| h == name).unwrap_or_default())
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?
+    };
+
+    let mut seen = HashSet::new();
+    let mut writer = get_writer(output)?;
+    let mut sink = RowSink::new(headers, &mut writer, format, opts)?;
+    for row in reader.records() {
+        let row = row?;
+        let key: Vec<String> = indexes
+            .iter()
+            .map(|&i| row.get(i).unwrap_or_default().to_string())
+            .collect();
+        if seen.insert(key) {
+            sink.write(&row)?;
+        }
+    }
+    sink.finish()?;
+    writer.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_csv_dedupe() -> anyhow::Result<()> {
+        let output = std::env::temp_dir().join("rcli_test_dedupe.ndjson");
+        process_csv_dedupe(
+            "assets/juventus.csv",
+            &["position".to_string()],
+            &CsvReadOpts::default(),
+            &output.to_string_lossy(),
+            Some(OutputFormat::Ndjson),
+        )?;
+        let content = std::fs::read_to_string(output)?;
+        let first: serde_json::Value =
+            serde_json::from_str(content.lines().next().unwrap_or_default())?;
+        assert_eq!(first["Name"], "Wojciech Szczesny");
+        assert_eq!(first["Kit Number"], 1);
+        assert_eq!(content.lines().count(), 10);
+        Ok(())
+    }
+}
@@ -0,0 +1,291 @@
+use crate::{
+    cli::{CsvReadOpts, CsvTransformOpts},
+    get_data_reader, CsvRecords,
+};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+use super::{csv_filter::resolve_column, csv_table::cell_text};
+
+/// Differences between two versions of a CSV file, matched on key columns.
+#[derive(Debug, Default, Serialize, PartialEq)]
+pub struct CsvDiff {
+    pub added_columns: Vec<String>,
+    pub removed_columns: Vec<String>,
+    pub added: Vec<Map<String, Value>>,
+    pub removed: Vec<Map<String, Value>>,
+    pub changed: Vec<RowChange>,
+}
+
+/// A row present in both files whose values differ.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct RowChange {
+    /// The key columns of the row.
+    pub key: Map<String, Value>,
+    pub changes: Vec<CellChange>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct CellChange {
+    pub column: String,
+    pub old: Value,
+    pub new: Value,
+}
+
+impl CsvDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added_columns.is_empty()
+            && self.removed_columns.is_empty()
+            && self.added.is_empty()
+            && self.removed.is_empty()
+            && self.changed.is_empty()
+    }
+}
+
+/// Compare `old` and `new` row by row. The old file is held in memory, the new one is streamed.
+pub fn process_csv_diff(
+    old: &str,
+    new: &str,
+    keys: &[String],
+    opts: &CsvReadOpts,
+) -> anyhow::Result<CsvDiff> {
+    // 不做类型推断，按原始文本比较，"1.50" 改成 "1.5" 也算修改
+    let transform = CsvTransformOpts {
+        no_infer: true,
+        ..Default::default()
+    };
+    let old_records = CsvRecords::open(get_data_reader(old)?, opts, &transform)?;
+    let new_records = CsvRecords::open(get_data_reader(new)?, opts, &transform)?;
+    let old_headers: Vec<String> = old_records.headers().map(String::from).collect();
+    let new_headers: Vec<String> = new_records.headers().map(String::from).collect();
+    let old_keys = resolve_keys(keys, &old_headers)?;
+    let new_keys = resolve_keys(keys, &new_headers)?;
+
+    let mut diff = CsvDiff {
+        added_columns: new_headers
+            .iter()
+            .filter(|h| !old_headers.contains(h))
+            .cloned()
+            .collect(),
+        removed_columns: old_headers
+            .iter()
+            .filter(|h| !new_headers.contains(h))
+            .cloned()
+            .collect(),
+        ..Default::default()
+    };
+    let common: Vec<&String> = new_headers
+        .iter()
+        .filter(|h| old_headers.contains(h))
+        .collect();
+
+    // 旧文件的行按键索引，匹配过的行取走，剩下的就是被删除的行
+    let mut rows = Vec::new();
+    let mut index = HashMap::new();
+    for record in old_records {
+        let record = record?;
+        let key = key_cells(&record, &old_keys);
+        anyhow::ensure!(
+            !index.contains_key(&key),
+            "duplicate key {} in {}",
+            key.join(", "),
+            old
+        );
+        index.insert(key, rows.len());
+        rows.push(Some(record));
+    }
+
+    let mut seen = HashSet::new();
+    for record in new_records {
+        let record = record?;
+        let key = key_cells(&record, &new_keys);
+        anyhow::ensure!(
+            seen.insert(key.clone()),
+            "duplicate key {} in {}",
+            key.join(", "),
+            new
+        );
+        let Some(old_record) = index.get(&key).and_then(|&i| rows[i].take()) else {
+            diff.added.push(record);
+            continue;
+        };
+        let changes: Vec<CellChange> = common
+            .iter()
+            .filter_map(|column| {
+                let old = old_record.get(*column).cloned().unwrap_or(Value::Null);
+                let new = record.get(*column).cloned().unwrap_or(Value::Null);
+                (old != new).then(|| CellChange {
+                    column: column.to_string(),
+                    old,
+                    new,
+                })
+            })
+            .collect();
+        if !changes.is_empty() {
+            let key = new_keys
+                .iter()
+                .map(|k| (k.clone(), record.get(k).cloned().unwrap_or(Value::Null)))
+                .collect();
+            diff.changed.push(RowChange { key, changes });
+        }
+    }
+    diff.removed = rows.into_iter().flatten().collect();
+    Ok(diff)
+}
+
+/// Render a diff for the terminal, `-` for removed rows, `+` for added rows and `~` for changes.
+pub fn render_diff(diff: &CsvDiff, color: bool) -> String {
+    let paint = |code: &str, text: String| {
+        if color {
+            format!("\x1b[{}m{}\x1b[0m", code, text)
+        } else {
+            text
+        }
+    };
+    let row = |record: &Map<String, Value>| {
+        record
+            .iter()
+            .map(|(k, v)| format!("{}={}", k, cell_text(v)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+
+    let mut out = String::new();
+    for column in &diff.removed_columns {
+        let _ = writeln!(out, "{}", paint("31", format!("- column {}", column)));
+    }
+    for column in &diff.added_columns {
+        let _ = writeln!(out, "{}", paint("32", format!("+ column {}", column)));
+    }
+    for record in &diff.removed {
+        let _ = writeln!(out, "{}", paint("31", format!("- {}", row(record))));
+    }
+    for record in &diff.added {
+        let _ = writeln!(out, "{}", paint("32", format!("+ {}", row(record))));
+    }
+    for change in &diff.changed {
+        let _ = writeln!(out, "{}", paint("33", format!("~ {}", row(&change.key))));
+        for cell in &change.changes {
+            let _ = writeln!(
+                out,
+                "    {}: {} -> {}",
+                cell.column,
+                paint("31", cell_text(&cell.old)),
+                paint("32", cell_text(&cell.new))
+            );
+        }
+    }
+    let _ = writeln!(
+        out,
+        "{} added, {} removed, {} changed",
+        diff.added.len(),
+        diff.removed.len(),
+        diff.changed.len()
+    );
+    out
+}
+
+fn resolve_keys(keys: &[String], headers: &[String]) -> anyhow::Result<Vec<String>> {
+    keys.iter()
+        .map(|key| Ok(resolve_column(key, headers.iter().map(String::as_str))?.to_string()))
+        .collect()
+}
+
+/// 键的每一列分别保存，避免 `a, b` 这样的值和组合键混淆
+fn key_cells(record: &Map<String, Value>, keys: &[String]) -> Vec<String> {
+    keys.iter()
+        .map(|k| raw_text(record.get(k).unwrap_or(&Value::Null)))
+        .collect()
+}
+
+/// 单元格的原始文本，不像 `cell_text` 那样替换换行和制表符
+fn raw_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_process_csv_diff() -> anyhow::Result<()> {
+        let diff = process_csv_diff(
+            "fixtures/squad_old.csv",
+            "fixtures/squad_new.csv",
+            &["id".to_string()],
+            &CsvReadOpts::default(),
+        )?;
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0]["name"], "Matthijs de Ligt");
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0]["id"], "3");
+        assert_eq!(
+            diff.changed[0],
+            RowChange {
+                key: json!({"id": "2"}).as_object().cloned().unwrap_or_default(),
+                changes: vec![CellChange {
+                    column: "age".into(),
+                    old: json!("25"),
+                    new: json!("26"),
+                }],
+            }
+        );
+        assert_eq!(diff.changed.len(), 2);
+
+        let report = render_diff(&diff, false);
+        assert!(report.contains("- id=3, name=Mario Mandzukic, position=Forward, age=33\n"));
+        assert!(report.contains("~ id=4\n    position: Defender -> Centre-Back\n"));
+        assert!(report.ends_with("1 added, 1 removed, 2 changed\n"));
+
+        let same = process_csv_diff(
+            "fixtures/squad_old.csv",
+            "fixtures/squad_old.csv",
+            &["id".to_string()],
+            &CsvReadOpts::default(),
+        )?;
+        assert!(same.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn test_diff_composite_keys_and_whitespace() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let old = dir.path().join("old.csv");
+        let new = dir.path().join("new.csv");
+        std::fs::write(
+            &old,
+            "a,b,note
+\"x, y\",z,\"one\ttwo\"
+x,\"y, z\",1.50
+",
+        )?;
+        std::fs::write(
+            &new,
+            "a,b,note
+\"x, y\",z,\"one two\"
+x,\"y, z\",1.5
+",
+        )?;
+        let diff = process_csv_diff(
+            old.to_str().unwrap(),
+            new.to_str().unwrap(),
+            &["a".to_string(), "b".to_string()],
+            &CsvReadOpts::default(),
+        )?;
+        assert_eq!(diff.changed.len(), 2);
+        assert_eq!(diff.changed[0].changes[0].old, json!("one\ttwo"));
+        assert_eq!(diff.changed[1].changes[0].old, json!("1.50"));
+        assert_eq!(diff.changed[1].changes[0].new, json!("1.5"));
+        assert!(diff.added.is_empty() && diff.removed.is_empty());
+        Ok(())
+    }
+}
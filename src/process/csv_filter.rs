@@ -0,0 +1,365 @@
+use anyhow::{anyhow, bail};
+use serde_json::{Map, Value};
+use std::{cmp::Ordering, iter::Peekable, str::Chars};
+
+/// A row predicate such as `age>30 && nationality=="Italy"`, evaluated against a typed record.
+///
+/// Columns are bare identifiers or backtick quoted (`` `Kit Number` ``), literals are numbers,
+/// quoted strings, `true`, `false` and `null`. Comparisons are `== != > >= < <=`, combined with
+/// `&& || !` (or `and or not`) and parentheses.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Filter {
+    Column(String),
+    Literal(Value),
+    Not(Box<Filter>),
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Compare(Box<Filter>, CompareOp, Box<Filter>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Str(String),
+    Num(f64),
+    Op(CompareOp),
+    And,
+    Or,
+    Not,
+    LParen,
+    RParen,
+}
+
+impl Filter {
+    pub fn parse(input: &str) -> anyhow::Result<Self> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let filter = parser.parse_or()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            bail!("Unexpected {:?} in filter: {}", token, input);
+        }
+        Ok(filter)
+    }
+
+    /// Resolve column names against the headers, falling back to a case-insensitive match.
+    pub fn bind<'a>(
+        self,
+        headers: impl IntoIterator<Item = &'a str> + Clone,
+    ) -> anyhow::Result<Self> {
+        Ok(match self {
+            Filter::Column(name) => Filter::Column(resolve_column(&name, headers)?.to_string()),
+            Filter::Literal(_) => self,
+            Filter::Not(f) => Filter::Not(Box::new(f.bind(headers)?)),
+            Filter::And(l, r) => Filter::And(
+                Box::new(l.bind(headers.clone())?),
+                Box::new(r.bind(headers)?),
+            ),
+            Filter::Or(l, r) => Filter::Or(
+                Box::new(l.bind(headers.clone())?),
+                Box::new(r.bind(headers)?),
+            ),
+            Filter::Compare(l, op, r) => Filter::Compare(
+                Box::new(l.bind(headers.clone())?),
+                op,
+                Box::new(r.bind(headers)?),
+            ),
+        })
+    }
+
+    pub fn matches(&self, record: &Map<String, Value>) -> bool {
+        truthy(&self.eval(record))
+    }
+
+    fn eval(&self, record: &Map<String, Value>) -> Value {
+        match self {
+            Filter::Column(name) => record.get(name).cloned().unwrap_or(Value::Null),
+            Filter::Literal(v) => v.clone(),
+            Filter::Not(f) => Value::Bool(!f.matches(record)),
+            Filter::And(l, r) => Value::Bool(l.matches(record) && r.matches(record)),
+            Filter::Or(l, r) => Value::Bool(l.matches(record) || r.matches(record)),
+            Filter::Compare(l, op, r) => {
+                let ordering = compare(&l.eval(record), &r.eval(record));
+                Value::Bool(match (op, ordering) {
+                    (CompareOp::Eq, Some(o)) => o.is_eq(),
+                    (CompareOp::Ne, Some(o)) => o.is_ne(),
+                    (CompareOp::Ne, None) => true,
+                    (CompareOp::Gt, Some(o)) => o.is_gt(),
+                    (CompareOp::Ge, Some(o)) => o.is_ge(),
+                    (CompareOp::Lt, Some(o)) => o.is_lt(),
+                    (CompareOp::Le, Some(o)) => o.is_le(),
+                    (_, None) => false,
+                })
+            }
+        }
+    }
+}
+
+/// Find the header matching `name`, exactly or else ignoring ASCII case.
+pub fn resolve_column<'a>(
+    name: &str,
+    headers: impl IntoIterator<Item = &'a str> + Clone,
+) -> anyhow::Result<&'a str> {
+    headers
+        .clone()
+        .into_iter()
+        .find(|h| *h == name)
+        .or_else(|| headers.into_iter().find(|h| h.eq_ignore_ascii_case(name)))
+        .ok_or_else(|| anyhow!("Unknown column: {}", name))
+}
+
+/// Compare two cell values. Numbers compare numerically (numeric strings included), `null` only
+/// equals `null`, and everything else falls back to comparing the text.
+fn compare(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Null, Value::Null) => Some(Ordering::Equal),
+        (Value::Null, _) | (_, Value::Null) => None,
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        _ => match (as_number(a), as_number(b)) {
+            (Some(a), Some(b)) => a.partial_cmp(&b),
+            _ => Some(as_text(a).cmp(&as_text(b))),
+        },
+    }
+}
+
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn as_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        _ => value.to_string(),
+    }
+}
+
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64() != Some(0.0),
+        Value::String(s) => !s.is_empty(),
+        _ => true,
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.tokens.get(self.pos) == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_or(&mut self) -> anyhow::Result<Filter> {
+        let mut left = self.parse_and()?;
+        while self.eat(&Token::Or) {
+            left = Filter::Or(Box::new(left), Box::new(self.parse_and()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> anyhow::Result<Filter> {
+        let mut left = self.parse_unary()?;
+        while self.eat(&Token::And) {
+            left = Filter::And(Box::new(left), Box::new(self.parse_unary()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> anyhow::Result<Filter> {
+        if self.eat(&Token::Not) {
+            return Ok(Filter::Not(Box::new(self.parse_unary()?)));
+        }
+        if self.eat(&Token::LParen) {
+            let inner = self.parse_or()?;
+            if !self.eat(&Token::RParen) {
+                bail!("Missing closing parenthesis in filter");
+            }
+            return Ok(inner);
+        }
+        let left = self.parse_operand()?;
+        if let Some(Token::Op(op)) = self.tokens.get(self.pos).cloned() {
+            self.pos += 1;
+            let right = self.parse_operand()?;
+            return Ok(Filter::Compare(Box::new(left), op, Box::new(right)));
+        }
+        Ok(left)
+    }
+
+    fn parse_operand(&mut self) -> anyhow::Result<Filter> {
+        Ok(match self.next() {
+            Some(Token::Ident(name)) => match name.as_str() {
+                "true" => Filter::Literal(Value::Bool(true)),
+                "false" => Filter::Literal(Value::Bool(false)),
+                "null" => Filter::Literal(Value::Null),
+                _ => Filter::Column(name),
+            },
+            Some(Token::Str(s)) => Filter::Literal(Value::String(s)),
+            Some(Token::Num(n)) => Filter::Literal(
+                serde_json::Number::from_f64(n)
+                    .map(Value::Number)
+                    .unwrap_or(Value::Null),
+            ),
+            Some(token) => bail!("Expected a column or value in filter, found {:?}", token),
+            None => bail!("Unexpected end of filter"),
+        })
+    }
+}
+
+fn tokenize(input: &str) -> anyhow::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        let token = match c {
+            c if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            '(' | ')' => {
+                chars.next();
+                if c == '(' {
+                    Token::LParen
+                } else {
+                    Token::RParen
+                }
+            }
+            '"' | '\'' => Token::Str(read_quoted(&mut chars)?),
+            '`' => Token::Ident(read_quoted(&mut chars)?),
+            '&' | '|' => {
+                chars.next();
+                if chars.next() != Some(c) {
+                    bail!("Expected {}{} in filter", c, c);
+                }
+                if c == '&' {
+                    Token::And
+                } else {
+                    Token::Or
+                }
+            }
+            '=' | '!' | '>' | '<' => {
+                chars.next();
+                let eq = chars.next_if_eq(&'=').is_some();
+                match (c, eq) {
+                    ('=', _) => Token::Op(CompareOp::Eq),
+                    ('!', true) => Token::Op(CompareOp::Ne),
+                    ('!', false) => Token::Not,
+                    ('>', true) => Token::Op(CompareOp::Ge),
+                    ('>', false) => Token::Op(CompareOp::Gt),
+                    ('<', true) => Token::Op(CompareOp::Le),
+                    _ => Token::Op(CompareOp::Lt),
+                }
+            }
+            c if c.is_ascii_digit() || c == '-' || c == '.' => {
+                let mut number = String::new();
+                while let Some(c) =
+                    chars.next_if(|c| c.is_ascii_alphanumeric() || "-+.".contains(*c))
+                {
+                    number.push(c);
+                }
+                Token::Num(
+                    number
+                        .parse()
+                        .map_err(|_| anyhow!("Invalid number in filter: {}", number))?,
+                )
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut ident = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || "_.".contains(*c)) {
+                    ident.push(c);
+                }
+                match ident.as_str() {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    _ => Token::Ident(ident),
+                }
+            }
+            _ => bail!("Unexpected character {:?} in filter", c),
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn read_quoted(chars: &mut Peekable<Chars>) -> anyhow::Result<String> {
+    let quote = chars.next().expect("caller peeked the quote");
+    let mut s = String::new();
+    loop {
+        match chars.next() {
+            Some('\\') => s.push(
+                chars
+                    .next()
+                    .ok_or_else(|| anyhow!("Unterminated string in filter"))?,
+            ),
+            Some(c) if c == quote => return Ok(s),
+            Some(c) => s.push(c),
+            None => bail!("Unterminated string in filter"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn record(value: Value) -> Map<String, Value> {
+        value
+            .as_object()
+            .cloned()
+            .expect("test record is an object")
+    }
+
+    #[test]
+    fn test_filter_matches() -> anyhow::Result<()> {
+        let player = record(json!({"age": 34, "nationality": "Italy", "Kit Number": 3}));
+        let headers = ["age", "nationality", "Kit Number"];
+        let check = |expr: &str| -> anyhow::Result<bool> {
+            Ok(Filter::parse(expr)?.bind(headers)?.matches(&player))
+        };
+        assert!(check(r#"age>30 && nationality=="Italy""#)?);
+        assert!(!check("age >= 35 || Nationality == 'Spain'")?);
+        assert!(check("!(age < 30) and `Kit Number` != 10")?);
+        assert!(check("age == \"34\"")?);
+        assert!(check("not (nationality == null)")?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_filter_errors() {
+        assert!(Filter::parse("age >").is_err());
+        assert!(Filter::parse("(age > 1").is_err());
+        assert!(Filter::parse("name == 'Buffon").is_err());
+        assert!(Filter::parse("age & 1").is_err());
+        assert!(Filter::parse("missing == 1")
+            .and_then(|f| f.bind(["age"]))
+            .is_err());
+    }
+}
@@ -0,0 +1,146 @@
+use crate::{
+    cli::{CsvReadOpts, JoinKind, OutputFormat},
+    csv_reader, get_data_reader, get_writer, RowSink,
+};
+use csv::StringRecord;
+use std::collections::HashMap;
+
+use super::csv_filter::resolve_column;
+
+/// Join two CSV files on key columns present in both.
+///
+/// The right file is held in memory and the left file is streamed. Output columns are the left
+/// columns followed by the right columns other than the keys; right columns whose names clash
+/// with a left column get a `_right` suffix. Rows with an empty key never match.
+pub fn process_csv_join(
+    left: &str,
+    right: &str,
+    on: &[String],
+    how: JoinKind,
+    opts: &CsvReadOpts,
+    output: &str,
+    format: Option<OutputFormat>,
+) -> anyhow::Result<()> {
+    let (left_headers, mut left_reader) = csv_reader(get_data_reader(left)?, opts)?;
+    let (right_headers, mut right_reader) = csv_reader(get_data_reader(right)?, opts)?;
+    let left_keys = key_indexes(on, &left_headers)?;
+    let right_keys = key_indexes(on, &right_headers)?;
+    let right_values: Vec<usize> = (0..right_headers.len())
+        .filter(|i| !right_keys.contains(i))
+        .collect();
+
+    let mut headers = left_headers.clone();
+    for &i in &right_values {
+        let name = &right_headers[i];
+        if left_headers.iter().any(|h| h == name) {
+            headers.push_field(&format!("{}_right", name));
+        } else {
+            headers.push_field(name);
+        }
+    }
+
+    let right_rows = right_reader.records().collect::<Result<Vec<_>, _>>()?;
+    let mut index: HashMap<Vec<&str>, Vec<usize>> = HashMap::new();
+    for (n, row) in right_rows.iter().enumerate() {
+        if let Some(key) = key_of(row, &right_keys) {
+            index.entry(key).or_default().push(n);
+        }
+    }
+    let mut matched = vec![false; right_rows.len()];
+
+    let mut writer = get_writer(output)?;
+    let mut sink = RowSink::new(headers, &mut writer, format, opts)?;
+    for row in left_reader.records() {
+        let row = row?;
+        let matches = key_of(&row, &left_keys).and_then(|key| index.get(&key));
+        match matches {
+            Some(matches) => {
+                for &n in matches {
+                    matched[n] = true;
+                    let mut out = row.clone();
+                    out.extend(right_values.iter().map(|&i| &right_rows[n][i]));
+                    sink.write(&out)?;
+                }
+            }
+            None if matches!(how, JoinKind::Left | JoinKind::Outer) => {
+                let mut out = row.clone();
+                out.extend(right_values.iter().map(|_| ""));
+                sink.write(&out)?;
+            }
+            None => {}
+        }
+    }
+
+    if matches!(how, JoinKind::Outer) {
+        // 右表中没有匹配的行，左侧只填入键列
+        for (n, row) in right_rows.iter().enumerate().filter(|(n, _)| !matched[*n]) {
+            let mut out = vec![""; left_headers.len()];
+            for (&l, &r) in left_keys.iter().zip(&right_keys) {
+                out[l] = &row[r];
+            }
+            out.extend(right_values.iter().map(|&i| &right_rows[n][i]));
+            sink.write(&StringRecord::from(out))?;
+        }
+    }
+    sink.finish()?;
+    writer.finish()
+}
+
+fn key_indexes(on: &[String], headers: &StringRecord) -> anyhow::Result<Vec<usize>> {
+    on.iter()
+        .map(|key| {
+            let name = resolve_column(key, headers.iter())?;
+            Ok(headers.iter().position(|h| h == name).unwrap_or_default())
+        })
+        .collect()
+}
+
+fn key_of<'a>(row: &'a StringRecord, indexes: &[usize]) -> Option<Vec<&'a str>> {
+    let key: Vec<&str> = indexes
+        .iter()
+        .map(|&i| row.get(i).unwrap_or_default())
+        .collect();
+    (!key.iter().any(|k| k.is_empty())).then_some(key)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn join(how: JoinKind) -> anyhow::Result<Vec<String>> {
+        let output = std::env::temp_dir().join(format!("rcli_test_join_{}.csv", how));
+        process_csv_join(
+            "assets/juventus.csv",
+            "fixtures/caps.csv",
+            &["name".to_string()],
+            how,
+            &CsvReadOpts::default(),
+            &output.to_string_lossy(),
+            None,
+        )?;
+        Ok(std::fs::read_to_string(output)?
+            .lines()
+            .map(String::from)
+            .collect())
+    }
+
+    #[test]
+    fn test_process_csv_join() -> anyhow::Result<()> {
+        let inner = join(JoinKind::Inner)?;
+        assert_eq!(
+            inner[0],
+            "Name,Position,DOB,Nationality,Kit Number,caps,Position_right"
+        );
+        assert_eq!(inner.len(), 3);
+        assert!(inner[1].starts_with("Gianluigi Buffon,") && inner[1].ends_with(",176,GK"));
+
+        let left = join(JoinKind::Left)?;
+        assert_eq!(left.len(), 28);
+        assert!(left[1].ends_with(",1,,"));
+
+        let outer = join(JoinKind::Outer)?;
+        assert_eq!(outer.len(), 29);
+        assert_eq!(outer[28], "Alessandro Del Piero,,,,,91,FW");
+        Ok(())
+    }
+}
@@ -0,0 +1,137 @@
+use crate::{
+    cli::{CsvReadOpts, OutputFormat},
+    csv_reader, get_data_reader, get_writer, RowSink,
+};
+use csv::StringRecord;
+use std::{collections::HashMap, io::Write};
+
+/// Where the rows of a merged input come from.
+enum Source {
+    /// A file, opened again for its rows once all headers are known.
+    File(String),
+    /// Rows of stdin, which can't be read twice.
+    Buffered(Vec<StringRecord>),
+}
+
+/// Concatenate CSV files. The output has the union of their columns in first-seen order, and
+/// cells of columns a file lacks are left empty.
+pub fn process_csv_merge(
+    inputs: &[String],
+    opts: &CsvReadOpts,
+    output: &str,
+    format: Option<OutputFormat>,
+) -> anyhow::Result<()> {
+    if inputs.iter().filter(|input| *input == "-").count() > 1 {
+        anyhow::bail!("stdin (-) can only be merged once");
+    }
+    // 第一遍只读表头，每个文件读完即关闭；标准输入无法重新读取，只能缓存它的行
+    let mut files = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let (headers, mut reader) = csv_reader(get_data_reader(input)?, opts)?;
+        let source = if input == "-" {
+            Source::Buffered(reader.records().collect::<Result<_, _>>()?)
+        } else {
+            Source::File(input.clone())
+        };
+        files.push((headers, source));
+    }
+    let mut writer = get_writer(output)?;
+    merge_sources(files, opts, &mut writer, format)?;
+    writer.finish()
+}
+
+fn merge_sources(
+    files: Vec<(StringRecord, Source)>,
+    opts: &CsvReadOpts,
+    writer: impl Write,
+    format: Option<OutputFormat>,
+) -> anyhow::Result<()> {
+    let mut headers = StringRecord::new();
+    let mut positions: HashMap<String, usize> = HashMap::new();
+    for (file_headers, _) in &files {
+        for header in file_headers.iter() {
+            if !positions.contains_key(header) {
+                positions.insert(header.to_string(), headers.len());
+                headers.push_field(header);
+            }
+        }
+    }
+
+    let width = headers.len();
+    let mut sink = RowSink::new(headers, writer, format, opts)?;
+    // 第二遍逐个输入输出行，同一时间只打开一个文件
+    for (file_headers, source) in files {
+        let rows: Box<dyn Iterator<Item = csv::Result<StringRecord>>> = match source {
+            Source::File(path) => {
+                let (headers, reader) = csv_reader(get_data_reader(&path)?, opts)?;
+                anyhow::ensure!(
+                    headers == file_headers,
+                    "the header of {} changed while merging",
+                    path
+                );
+                Box::new(reader.into_records())
+            }
+            Source::Buffered(rows) => Box::new(rows.into_iter().map(Ok)),
+        };
+        let targets: Vec<usize> = file_headers.iter().map(|h| positions[h]).collect();
+        for row in rows {
+            let row = row?;
+            let mut cells = vec![""; width];
+            for (&target, value) in targets.iter().zip(row.iter()) {
+                cells[target] = value;
+            }
+            sink.write(&StringRecord::from(cells))?;
+        }
+    }
+    sink.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_csv_merge() -> anyhow::Result<()> {
+        let output = std::env::temp_dir().join("rcli_test_merge.csv");
+        process_csv_merge(
+            &[
+                "fixtures/squad_old.csv".to_string(),
+                "fixtures/caps.csv".to_string(),
+            ],
+            &CsvReadOpts::default(),
+            &output.to_string_lossy(),
+            None,
+        )?;
+        let content = std::fs::read_to_string(output)?;
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines[0], "id,name,position,age,caps,Position");
+        assert_eq!(lines[1], "1,Gianluigi Buffon,Goalkeeper,41,,");
+        assert_eq!(lines[5], ",Giorgio Chiellini,,,117,DF");
+        assert_eq!(lines.len(), 8);
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_reads_each_input_once() -> anyhow::Result<()> {
+        // 只能读一次的输入，模拟标准输入
+        let stdin = std::io::Cursor::new(std::fs::read("fixtures/squad_old.csv")?);
+        let opts = CsvReadOpts::default();
+        let (stdin_headers, mut stdin_reader) = csv_reader(stdin, &opts)?;
+        let rows = stdin_reader.records().collect::<Result<_, _>>()?;
+        let (caps_headers, _) = csv_reader(std::fs::File::open("fixtures/caps.csv")?, &opts)?;
+        let files = vec![
+            (stdin_headers, Source::Buffered(rows)),
+            (caps_headers, Source::File("fixtures/caps.csv".to_string())),
+        ];
+        let mut output = Vec::new();
+        merge_sources(files, &opts, &mut output, None)?;
+        let content = String::from_utf8(output)?;
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines[1], "1,Gianluigi Buffon,Goalkeeper,41,,");
+        assert_eq!(lines.len(), 8);
+
+        let inputs = ["-".to_string(), "-".to_string()];
+        assert!(process_csv_merge(&inputs, &opts, "-", None).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,176 @@
+use crate::{
+    cli::{CsvReadOpts, CsvTransformOpts, OutputFormat},
+    get_data_reader, record_writer, CsvRecords,
+};
+use anyhow::Context;
+use rusqlite::{types::ValueRef, Connection, ToSql};
+use serde_json::{Map, Value};
+use std::{io::Write, path::Path};
+
+/// The columns and rows returned by a query.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryResult {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Value>>,
+}
+
+/// Load each CSV file into an in-memory SQLite table named after the file, then run `sql`.
+pub fn process_csv_query(
+    inputs: &[String],
+    sql: &str,
+    opts: &CsvReadOpts,
+) -> anyhow::Result<QueryResult> {
+    let mut conn = Connection::open_in_memory()?;
+    for input in inputs {
+        load_table(&mut conn, input, opts)?;
+    }
+
+    let mut stmt = conn.prepare(sql)?;
+    let columns: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+    let mut rows = Vec::new();
+    let mut result = stmt.query([])?;
+    while let Some(row) = result.next()? {
+        let row = (0..columns.len())
+            .map(|i| Ok(to_json(row.get_ref(i)?)))
+            .collect::<rusqlite::Result<_>>()?;
+        rows.push(row);
+    }
+    Ok(QueryResult { columns, rows })
+}
+
+impl QueryResult {
+    /// Write each row as a record keyed by column name.
+    pub fn write(self, format: OutputFormat, writer: impl Write) -> anyhow::Result<()> {
+        let mut writer = record_writer(format, writer);
+        for row in self.rows {
+            let record: Map<String, Value> = self
+                .columns
+                .iter()
+                .cloned()
+                .zip(row)
+                // TOML 无法表示 null，直接省略该字段
+                .filter(|(_, v)| !(matches!(format, OutputFormat::Toml) && v.is_null()))
+                .collect();
+            writer.write_record(&Value::Object(record))?;
+        }
+        writer.finish()
+    }
+}
+
+/// The table name for a file: its name up to the first dot, so `data/juventus.csv.gz` is `juventus`.
+pub fn table_name(input: &str) -> String {
+    if input == "-" {
+        return "stdin".to_string();
+    }
+    let name = Path::new(input)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    match name.split_once('.') {
+        Some((stem, _)) if !stem.is_empty() => stem.to_string(),
+        _ => name,
+    }
+}
+
+fn load_table(conn: &mut Connection, input: &str, opts: &CsvReadOpts) -> anyhow::Result<()> {
+    let table = table_name(input);
+    let records = CsvRecords::open(get_data_reader(input)?, opts, &CsvTransformOpts::default())?;
+    let headers: Vec<String> = records.headers().map(String::from).collect();
+
+    // 列不声明类型，单元格按推断出的类型原样存储
+    let columns = headers
+        .iter()
+        .map(|h| quote_ident(h))
+        .collect::<Vec<_>>()
+        .join(", ");
+    conn.execute(
+        &format!("CREATE TABLE {} ({})", quote_ident(&table), columns),
+        [],
+    )
+    .with_context(|| format!("cannot create table {} for {}", table, input))?;
+
+    let placeholders = vec!["?"; headers.len()].join(", ");
+    let tx = conn.transaction()?;
+    {
+        let mut insert = tx.prepare(&format!(
+            "INSERT INTO {} VALUES ({})",
+            quote_ident(&table),
+            placeholders
+        ))?;
+        for record in records {
+            let values: Vec<SqlValue> = record?.into_iter().map(|(_, v)| SqlValue(v)).collect();
+            insert.execute(rusqlite::params_from_iter(values.iter()))?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn to_json(value: ValueRef) -> Value {
+    match value {
+        ValueRef::Null => Value::Null,
+        ValueRef::Integer(n) => Value::from(n),
+        ValueRef::Real(f) => Value::from(f),
+        ValueRef::Text(s) | ValueRef::Blob(s) => Value::from(String::from_utf8_lossy(s)),
+    }
+}
+
+/// A typed cell bound as an SQL parameter.
+struct SqlValue(Value);
+
+impl ToSql for SqlValue {
+    fn to_sql(&self) -> rusqlite::Result<rusqlite::types::ToSqlOutput<'_>> {
+        use rusqlite::types::{ToSqlOutput, Value as Sql};
+        Ok(ToSqlOutput::Owned(match &self.0 {
+            Value::Null => Sql::Null,
+            Value::Bool(b) => Sql::Integer(*b as i64),
+            Value::Number(n) => match n.as_i64() {
+                Some(i) => Sql::Integer(i),
+                None => Sql::Real(n.as_f64().unwrap_or_default()),
+            },
+            Value::String(s) => Sql::Text(s.clone()),
+            other => Sql::Text(other.to_string()),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_process_csv_query() -> anyhow::Result<()> {
+        let result = process_csv_query(
+            &["assets/juventus.csv".to_string()],
+            "SELECT Nationality, count(*) AS players, max(`Kit Number`) FROM juventus \
+             GROUP BY Nationality ORDER BY 2 DESC, 1 LIMIT 2",
+            &CsvReadOpts::default(),
+        )?;
+        assert_eq!(
+            result.columns,
+            vec!["Nationality", "players", "max(`Kit Number`)"]
+        );
+        assert_eq!(result.rows[0], vec![json!("Italy"), json!(8), json!(77)]);
+
+        let mut output = Vec::new();
+        result.write(OutputFormat::Ndjson, &mut output)?;
+        let first = String::from_utf8(output)?.lines().next().map(String::from);
+        assert_eq!(
+            first.as_deref(),
+            Some(r#"{"Nationality":"Italy","players":8,"max(`Kit Number`)":77}"#)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_table_name() {
+        assert_eq!(table_name("assets/juventus.csv"), "juventus");
+        assert_eq!(table_name("data/players.csv.gz"), "players");
+        assert_eq!(table_name("-"), "stdin");
+    }
+}
@@ -0,0 +1,136 @@
+use crate::{
+    cli::{CsvReadOpts, OutputFormat},
+    record_writer, RecordConverter, RecordWriter,
+};
+use csv::{StringRecord, Writer, WriterBuilder};
+use serde_json::Value;
+use std::{cmp::Ordering, io::Write};
+
+/// 输出为其它格式时，用于推断列类型的样本行数
+const SINK_SAMPLE_ROWS: usize = 1000;
+
+/// Where the rows produced by sort, dedupe and join go: CSV, or typed records in an
+/// `OutputFormat`.
+pub struct RowSink<'a> {
+    headers: StringRecord,
+    kind: SinkKind<'a>,
+}
+
+enum SinkKind<'a> {
+    Csv(Box<Writer<Box<dyn Write + 'a>>>),
+    Records {
+        format: OutputFormat,
+        /// 推断出列类型之前缓存的行
+        sample: Vec<StringRecord>,
+        converter: Option<RecordConverter>,
+        writer: Box<dyn RecordWriter + 'a>,
+    },
+}
+
+impl<'a> RowSink<'a> {
+    /// Write CSV using the input's delimiter and header settings when `format` is `None` or CSV.
+    pub fn new(
+        headers: StringRecord,
+        writer: impl Write + 'a,
+        format: Option<OutputFormat>,
+        opts: &CsvReadOpts,
+    ) -> anyhow::Result<Self> {
+        let kind = match format {
+            None | Some(OutputFormat::Csv) => {
+                let mut writer = WriterBuilder::new()
+                    .delimiter(opts.delimiter as u8)
+                    .from_writer(Box::new(writer) as Box<dyn Write + 'a>);
+                if opts.header {
+                    writer.write_record(&headers)?;
+                }
+                SinkKind::Csv(Box::new(writer))
+            }
+            Some(format) => SinkKind::Records {
+                format,
+                sample: Vec::new(),
+                converter: None,
+                writer: record_writer(format, writer),
+            },
+        };
+        Ok(Self { headers, kind })
+    }
+
+    pub fn write(&mut self, row: &StringRecord) -> anyhow::Result<()> {
+        match &mut self.kind {
+            SinkKind::Csv(writer) => writer.write_record(row)?,
+            SinkKind::Records {
+                format,
+                sample,
+                converter,
+                writer,
+            } => match converter {
+                Some(converter) => write_record(converter, writer.as_mut(), *format, row)?,
+                None => {
+                    sample.push(row.clone());
+                    if sample.len() >= SINK_SAMPLE_ROWS {
+                        let ready = RecordConverter::new(&self.headers, sample, true, &[])?;
+                        for row in sample.drain(..) {
+                            write_record(&ready, writer.as_mut(), *format, &row)?;
+                        }
+                        *converter = Some(ready);
+                    }
+                }
+            },
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> anyhow::Result<()> {
+        match self.kind {
+            SinkKind::Csv(mut writer) => writer.flush()?,
+            SinkKind::Records {
+                format,
+                sample,
+                converter,
+                mut writer,
+            } => {
+                if converter.is_none() {
+                    let converter = RecordConverter::new(&self.headers, &sample, true, &[])?;
+                    for row in &sample {
+                        write_record(&converter, writer.as_mut(), format, row)?;
+                    }
+                }
+                writer.finish()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn write_record(
+    converter: &RecordConverter,
+    writer: &mut dyn RecordWriter,
+    format: OutputFormat,
+    row: &StringRecord,
+) -> anyhow::Result<()> {
+    let mut record = converter.convert(row)?;
+    // TOML 无法表示 null，直接省略该字段
+    if matches!(format, OutputFormat::Toml) {
+        record.retain(|_, v| !v.is_null());
+    }
+    writer.write_record(&Value::Object(record))
+}
+
+/// Order two cells: empty cells first, then numbers by value, then everything else as text.
+pub fn compare_cells(a: &str, b: &str) -> Ordering {
+    fn rank(cell: &str) -> (u8, Option<f64>) {
+        if cell.is_empty() {
+            (0, None)
+        } else {
+            match cell.trim().parse::<f64>() {
+                Ok(n) if !n.is_nan() => (1, Some(n)),
+                _ => (2, None),
+            }
+        }
+    }
+    match (rank(a), rank(b)) {
+        ((1, Some(x)), (1, Some(y))) => x.total_cmp(&y),
+        ((2, _), (2, _)) => a.cmp(b),
+        ((ra, _), (rb, _)) => ra.cmp(&rb),
+    }
+}
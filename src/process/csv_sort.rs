@@ -0,0 +1,278 @@
+use crate::{
+    cli::{CsvReadOpts, OutputFormat, SortKey},
+    compare_cells, csv_reader, get_data_reader, get_writer, RowSink,
+};
+use anyhow::Context;
+use csv::{Reader, ReaderBuilder, StringRecord, Writer, WriterBuilder};
+use std::{
+    cmp::Ordering,
+    collections::BinaryHeap,
+    fs::File,
+    io::{BufReader, BufWriter, Seek, SeekFrom},
+};
+
+use super::csv_filter::resolve_column;
+
+/// 估算内存占用时每行额外计入的字节数
+const ROW_OVERHEAD_BYTES: usize = 64;
+
+/// 每轮归并最多同时打开的临时文件数，块更多时分多轮归并
+const MERGE_FAN_IN: usize = 64;
+
+/// Sort a CSV file by one or more columns.
+///
+/// Rows are sorted in memory until they exceed `memory_budget` bytes; larger files are sorted in
+/// chunks spilled to temporary files and merged. The sort is stable.
+pub fn process_csv_sort(
+    input: &str,
+    keys: &[SortKey],
+    memory_budget: usize,
+    opts: &CsvReadOpts,
+    output: &str,
+    format: Option<OutputFormat>,
+) -> anyhow::Result<()> {
+    let (headers, mut reader) = csv_reader(get_data_reader(input)?, opts)?;
+    let keys = keys
+        .iter()
+        .map(|key| {
+            let name = resolve_column(&key.column, headers.iter())?;
+            let index = headers.iter().position(|h| h == name).unwrap_or_default();
+            Ok((index, key.descending))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let compare = |a: &StringRecord, b: &StringRecord| compare_rows(&keys, a, b);
+
+    let mut chunks = Vec::new();
+    let mut rows = Vec::new();
+    let mut used = 0;
+    for row in reader.records() {
+        let row = row?;
+        used += row.as_slice().len() + ROW_OVERHEAD_BYTES;
+        rows.push(row);
+        if used > memory_budget {
+            rows.sort_by(compare);
+            chunks.push(spill(&rows)?);
+            rows.clear();
+            used = 0;
+        }
+    }
+    rows.sort_by(compare);
+
+    let mut writer = get_writer(output)?;
+    let mut sink = RowSink::new(headers, &mut writer, format, opts)?;
+    if chunks.is_empty() {
+        for row in &rows {
+            sink.write(row)?;
+        }
+        sink.finish()?;
+        return writer.finish();
+    }
+
+    if !rows.is_empty() {
+        chunks.push(spill(&rows)?);
+    }
+    drop(rows);
+    merge_chunks(chunks, &keys, MERGE_FAN_IN, |row| sink.write(row))?;
+    sink.finish()?;
+    writer.finish()
+}
+
+/// Compare two rows by the sort keys, given as column index and whether it's descending.
+fn compare_rows(keys: &[(usize, bool)], a: &StringRecord, b: &StringRecord) -> Ordering {
+    keys.iter()
+        .map(|&(i, descending)| {
+            let ordering =
+                compare_cells(a.get(i).unwrap_or_default(), b.get(i).unwrap_or_default());
+            if descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        })
+        .find(|o| o.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+/// The current row of a chunk during the merge.
+struct Head<'a> {
+    row: StringRecord,
+    chunk: usize,
+    keys: &'a [(usize, bool)],
+}
+
+impl Ord for Head<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // BinaryHeap 是大顶堆，反转后先取出最小的行；相等时取靠前的块，保持排序稳定
+        compare_rows(self.keys, &self.row, &other.row)
+            .then(self.chunk.cmp(&other.chunk))
+            .reverse()
+    }
+}
+
+impl PartialOrd for Head<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Head<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Head<'_> {}
+
+/// Merge sorted chunks in order, opening at most `fan_in` of them at a time, and pass every
+/// row to `emit`.
+fn merge_chunks(
+    mut chunks: Vec<File>,
+    keys: &[(usize, bool)],
+    fan_in: usize,
+    mut emit: impl FnMut(&StringRecord) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    // 块太多时先把相邻的块分组归并成更大的块，相邻分组保持块的先后顺序
+    while chunks.len() > fan_in {
+        let mut merged = Vec::with_capacity(chunks.len().div_ceil(fan_in));
+        let mut rest = chunks.into_iter();
+        loop {
+            let group: Vec<File> = rest.by_ref().take(fan_in).collect();
+            if group.is_empty() {
+                break;
+            }
+            merged.push(spill_with(|writer| {
+                merge_group(group, keys, |row| Ok(writer.write_record(row)?))
+            })?);
+        }
+        chunks = merged;
+    }
+    merge_group(chunks, keys, &mut emit)
+}
+
+fn merge_group(
+    chunks: Vec<File>,
+    keys: &[(usize, bool)],
+    mut emit: impl FnMut(&StringRecord) -> anyhow::Result<()>,
+) -> anyhow::Result<()> {
+    let mut readers = chunks
+        .into_iter()
+        .map(|file| {
+            ReaderBuilder::new()
+                .has_headers(false)
+                .from_reader(BufReader::new(file))
+        })
+        .collect::<Vec<_>>();
+    let mut heap = BinaryHeap::with_capacity(readers.len());
+    for (chunk, reader) in readers.iter_mut().enumerate() {
+        if let Some(row) = next_row(reader)? {
+            heap.push(Head { row, chunk, keys });
+        }
+    }
+    while let Some(Head { row, chunk, .. }) = heap.pop() {
+        emit(&row)?;
+        if let Some(row) = next_row(&mut readers[chunk])? {
+            heap.push(Head { row, chunk, keys });
+        }
+    }
+    Ok(())
+}
+
+/// 将排好序的一块写入临时文件，返回定位到开头的文件
+fn spill(rows: &[StringRecord]) -> anyhow::Result<File> {
+    spill_with(|writer| {
+        for row in rows {
+            writer.write_record(row)?;
+        }
+        Ok(())
+    })
+}
+
+fn spill_with(
+    fill: impl FnOnce(&mut Writer<BufWriter<File>>) -> anyhow::Result<()>,
+) -> anyhow::Result<File> {
+    let file = tempfile::tempfile().context("cannot create a temporary file for sorting")?;
+    let mut writer = WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(BufWriter::new(file));
+    fill(&mut writer)?;
+    let mut file = writer
+        .into_inner()
+        .map_err(|e| anyhow::anyhow!("{}", e.error()))?
+        .into_inner()?;
+    file.seek(SeekFrom::Start(0))?;
+    Ok(file)
+}
+
+fn next_row(reader: &mut Reader<BufReader<File>>) -> anyhow::Result<Option<StringRecord>> {
+    let mut row = StringRecord::new();
+    Ok(reader.read_record(&mut row)?.then_some(row))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sort(keys: &str, memory_budget: usize, name: &str) -> anyhow::Result<Vec<String>> {
+        let output = std::env::temp_dir().join(name);
+        let keys = keys
+            .split(',')
+            .map(str::parse)
+            .collect::<anyhow::Result<Vec<SortKey>>>()?;
+        process_csv_sort(
+            "assets/juventus.csv",
+            &keys,
+            memory_budget,
+            &CsvReadOpts::default(),
+            &output.to_string_lossy(),
+            None,
+        )?;
+        Ok(std::fs::read_to_string(output)?
+            .lines()
+            .map(String::from)
+            .collect())
+    }
+
+    #[test]
+    fn test_process_csv_sort() -> anyhow::Result<()> {
+        let lines = sort("Kit Number:desc", usize::MAX, "rcli_test_sort.csv")?;
+        assert_eq!(lines.len(), 28);
+        assert!(lines[1].starts_with("Gianluigi Buffon,"));
+        assert!(lines[27].starts_with("Wojciech Szczesny,"));
+
+        // 数值按大小而不是按文本排序
+        let lines = sort("kit number", usize::MAX, "rcli_test_sort_asc.csv")?;
+        assert!(lines[2].ends_with(",2"));
+        assert!(lines[9].ends_with(",10"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_csv_sort_external_matches_in_memory() -> anyhow::Result<()> {
+        let in_memory = sort("Position,Name:desc", usize::MAX, "rcli_test_sort_mem.csv")?;
+        let external = sort("Position,Name:desc", 300, "rcli_test_sort_ext.csv")?;
+        assert_eq!(in_memory, external);
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_chunks_in_several_passes() -> anyhow::Result<()> {
+        let (_, mut reader) =
+            csv_reader(File::open("assets/juventus.csv")?, &CsvReadOpts::default())?;
+        let rows = reader.records().collect::<Result<Vec<_>, _>>()?;
+        // 按位置排序，位置相同的行保持原来的顺序
+        let keys = [(1, false)];
+        let chunks = rows
+            .iter()
+            .map(|row| spill(std::slice::from_ref(row)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let mut merged = Vec::new();
+        merge_chunks(chunks, &keys, 2, |row| {
+            merged.push(row.clone());
+            Ok(())
+        })?;
+        let mut expected = rows;
+        expected.sort_by(|a, b| compare_rows(&keys, a, b));
+        assert_eq!(merged, expected);
+        Ok(())
+    }
+}
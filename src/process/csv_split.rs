@@ -0,0 +1,263 @@
+use crate::{cli::CsvReadOpts, csv_reader, get_data_reader, get_writer, Output};
+use csv::{StringRecord, Writer, WriterBuilder};
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::BufWriter,
+    path::Path,
+};
+
+use super::csv_filter::resolve_column;
+
+/// 按列拆分时同时打开的文件数上限，超过后关闭最久未写入的文件，之后需要时再追加打开
+const MAX_OPEN_FILES: usize = 64;
+
+/// How `process_csv_split` decides which file a row goes to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SplitBy {
+    /// At most this many rows per file.
+    Rows(usize),
+    /// One file per distinct value of the column.
+    Column(String),
+}
+
+/// Split a CSV file into `{prefix}_0001.csv`, `{prefix}_0002.csv`, ... or `{prefix}_{value}.csv`
+/// in `dir`, repeating the header in each. Returns the paths written, in order.
+///
+/// Values that only differ in characters unsafe for file names (or in case) would share a file,
+/// so they are an error rather than being merged.
+pub fn process_csv_split(
+    input: &str,
+    by: &SplitBy,
+    opts: &CsvReadOpts,
+    dir: &Path,
+    prefix: &str,
+) -> anyhow::Result<Vec<String>> {
+    let (headers, mut reader) = csv_reader(get_data_reader(input)?, opts)?;
+    let column = match by {
+        SplitBy::Rows(0) => anyhow::bail!("--rows must be greater than 0"),
+        SplitBy::Rows(_) => None,
+        SplitBy::Column(name) => {
+            let name = resolve_column(name, headers.iter())?;
+            headers.iter().position(|h| h == name)
+        }
+    };
+
+    let mut paths = Vec::new();
+    let mut files = ColumnFiles {
+        dir,
+        prefix,
+        headers: &headers,
+        opts,
+        owners: HashMap::new(),
+        open: HashMap::new(),
+        tick: 0,
+    };
+    let mut current: Option<Writer<Output>> = None;
+    for (count, row) in reader.records().enumerate() {
+        let row = row?;
+        match (by, column) {
+            (SplitBy::Rows(rows), _) => {
+                if count % rows == 0 {
+                    if let Some(writer) = current.take() {
+                        close(writer)?;
+                    }
+                    let path = dir.join(format!("{}_{:04}.csv", prefix, count / rows + 1));
+                    current = Some(create(&path, &headers, opts, &mut paths)?);
+                }
+                if let Some(writer) = current.as_mut() {
+                    writer.write_record(&row)?;
+                }
+            }
+            (SplitBy::Column(_), Some(i)) => {
+                files.write(row.get(i).unwrap_or_default(), &row, &mut paths)?
+            }
+            (SplitBy::Column(_), None) => unreachable!("column resolved above"),
+        }
+    }
+
+    if let Some(writer) = current {
+        close(writer)?;
+    }
+    files.close()?;
+    Ok(paths)
+}
+
+/// One file per distinct column value, keeping at most `MAX_OPEN_FILES` of them open.
+struct ColumnFiles<'a> {
+    dir: &'a Path,
+    prefix: &'a str,
+    headers: &'a StringRecord,
+    opts: &'a CsvReadOpts,
+    /// 文件名（小写）到第一个使用它的取值，用来发现清理后重名的取值
+    owners: HashMap<String, String>,
+    /// 打开的文件及其最后写入的序号
+    open: HashMap<String, (Writer<BufWriter<File>>, u64)>,
+    tick: u64,
+}
+
+impl ColumnFiles<'_> {
+    fn write(
+        &mut self,
+        value: &str,
+        row: &StringRecord,
+        paths: &mut Vec<String>,
+    ) -> anyhow::Result<()> {
+        let name = file_name(value);
+        let path = self.dir.join(format!("{}_{}.csv", self.prefix, name));
+        // 在不区分大小写的文件系统上 A 和 a 是同一个文件
+        let owner = name.to_lowercase();
+        match self.owners.get(&owner) {
+            Some(other) if other != value => anyhow::bail!(
+                "values {:?} and {:?} would both be written to {}",
+                other,
+                value,
+                path.display()
+            ),
+            _ => {}
+        }
+
+        self.tick += 1;
+        if !self.open.contains_key(&name) {
+            if self.open.len() >= MAX_OPEN_FILES {
+                let oldest = self
+                    .open
+                    .iter()
+                    .min_by_key(|(_, (_, used))| *used)
+                    .map(|(name, _)| name.clone())
+                    .expect("open files is full");
+                if let Some((mut writer, _)) = self.open.remove(&oldest) {
+                    writer.flush()?;
+                }
+            }
+            let writer = if self.owners.contains_key(&owner) {
+                let file = OpenOptions::new().append(true).open(&path)?;
+                WriterBuilder::new()
+                    .delimiter(self.opts.delimiter as u8)
+                    .from_writer(BufWriter::new(file))
+            } else {
+                let mut writer = WriterBuilder::new()
+                    .delimiter(self.opts.delimiter as u8)
+                    .from_writer(BufWriter::new(File::create(&path)?));
+                if self.opts.header {
+                    writer.write_record(self.headers)?;
+                }
+                self.owners.insert(owner, value.to_string());
+                paths.push(path.to_string_lossy().to_string());
+                writer
+            };
+            self.open.insert(name.clone(), (writer, 0));
+        }
+        let (writer, used) = self.open.get_mut(&name).expect("opened above");
+        *used = self.tick;
+        writer.write_record(row)?;
+        Ok(())
+    }
+
+    fn close(self) -> anyhow::Result<()> {
+        for (mut writer, _) in self.open.into_values() {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+fn create(
+    path: &Path,
+    headers: &StringRecord,
+    opts: &CsvReadOpts,
+    paths: &mut Vec<String>,
+) -> anyhow::Result<Writer<Output>> {
+    let path = path.to_string_lossy().to_string();
+    let mut writer = WriterBuilder::new()
+        .delimiter(opts.delimiter as u8)
+        .from_writer(get_writer(&path)?);
+    if opts.header {
+        writer.write_record(headers)?;
+    }
+    paths.push(path);
+    Ok(writer)
+}
+
+fn close(writer: Writer<Output>) -> anyhow::Result<()> {
+    writer.into_inner().map_err(|e| e.into_error())?.finish()
+}
+
+/// 将取值转换为安全的文件名片段
+fn file_name(value: &str) -> String {
+    if value.is_empty() {
+        return "empty".to_string();
+    }
+    value
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_csv_split() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir().join("rcli_test_split");
+        std::fs::create_dir_all(&dir)?;
+        let paths = process_csv_split(
+            "assets/juventus.csv",
+            &SplitBy::Rows(10),
+            &CsvReadOpts::default(),
+            &dir,
+            "juventus",
+        )?;
+        assert_eq!(paths.len(), 3);
+        assert!(paths[2].ends_with("juventus_0003.csv"));
+        let last = std::fs::read_to_string(&paths[2])?;
+        assert!(last.starts_with("Name,Position,DOB,Nationality,Kit Number\n"));
+        assert_eq!(last.lines().count(), 8);
+
+        let paths = process_csv_split(
+            "assets/juventus.csv",
+            &SplitBy::Column("position".into()),
+            &CsvReadOpts::default(),
+            &dir,
+            "juventus",
+        )?;
+        assert_eq!(paths.len(), 10);
+        assert!(paths[0].ends_with("juventus_Goalkeeper.csv"));
+        let goalkeepers = std::fs::read_to_string(&paths[0])?;
+        assert_eq!(goalkeepers.lines().count(), 5);
+        assert_eq!(file_name("Centre-Back"), "Centre-Back");
+        assert_eq!(file_name("a/b c"), "a_b_c");
+        Ok(())
+    }
+
+    #[test]
+    fn test_split_by_many_values_and_collisions() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let input = dir.path().join("ids.csv");
+        // 取值个数超过同时打开的文件上限，并且交替出现
+        let mut content = "id,n\n".to_string();
+        for n in 0..(MAX_OPEN_FILES * 3) {
+            content.push_str(&format!("v{},{}\n", n % (MAX_OPEN_FILES * 2), n));
+        }
+        std::fs::write(&input, content)?;
+        let by = SplitBy::Column("id".into());
+        let opts = CsvReadOpts::default();
+        let paths = process_csv_split(input.to_str().unwrap(), &by, &opts, dir.path(), "ids")?;
+        assert_eq!(paths.len(), MAX_OPEN_FILES * 2);
+        let first = std::fs::read_to_string(dir.path().join("ids_v0.csv"))?;
+        assert_eq!(first, format!("id,n\nv0,0\nv0,{}\n", MAX_OPEN_FILES * 2));
+
+        std::fs::write(&input, "id\na/b\na b\n")?;
+        let err = process_csv_split(input.to_str().unwrap(), &by, &opts, dir.path(), "ids")
+            .expect_err("a/b and a b share a file name");
+        assert!(err.to_string().contains("\"a/b\" and \"a b\""));
+        Ok(())
+    }
+}
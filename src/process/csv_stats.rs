@@ -0,0 +1,372 @@
+use crate::{
+    cli::{ColumnType, CsvReadOpts},
+    csv_reader, get_data_reader, render_table, terminal_width, TypeGuess,
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+    collections::{hash_map::DefaultHasher, HashSet},
+    hash::{Hash, Hasher},
+    mem,
+};
+
+use super::sketch::{FrequentValues, HyperLogLog, P2Median};
+
+/// 每列精确统计的取值个数上限，超过后改用固定大小的估算，避免大文件占满内存
+const MAX_TRACKED_VALUES: usize = 100_000;
+
+/// Summary statistics of one column.
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ColumnStats {
+    pub column: String,
+    #[serde(rename = "type")]
+    pub ty: String,
+    /// Number of non-empty cells.
+    pub count: u64,
+    pub nulls: u64,
+    pub distinct: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mean: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub median: Option<f64>,
+    pub top: Vec<ValueCount>,
+    /// Statistics estimated because the column had too many values to track exactly:
+    /// `distinct` (HyperLogLog), `median` (P²) and `top` (Misra-Gries, counts are lower bounds).
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub approximate: Vec<String>,
+}
+
+#[derive(Debug, Serialize, PartialEq)]
+pub struct ValueCount {
+    pub value: String,
+    pub count: u64,
+}
+
+/// Running state for one column while the file is streamed, in bounded memory.
+#[derive(Debug)]
+struct ColumnAccumulator {
+    guess: TypeGuess,
+    count: u64,
+    nulls: u64,
+    distinct: Distinct,
+    frequencies: FrequentValues,
+    /// 列可能是数值时才收集，确定不是数值后清空
+    numbers: Option<Numbers>,
+}
+
+/// 不同取值的哈希，超过上限后改用 HyperLogLog 估算
+#[derive(Debug)]
+enum Distinct {
+    Exact(HashSet<u64>),
+    Estimated(HyperLogLog),
+}
+
+/// 数值列的最小值、最大值和总和是精确的，中位数超过上限后改用 P² 估算
+#[derive(Debug, Default)]
+struct Numbers {
+    count: u64,
+    sum: f64,
+    min: f64,
+    max: f64,
+    values: Vec<f64>,
+    median: Option<P2Median>,
+}
+
+impl Default for ColumnAccumulator {
+    fn default() -> Self {
+        Self {
+            guess: TypeGuess::default(),
+            count: 0,
+            nulls: 0,
+            distinct: Distinct::Exact(HashSet::new()),
+            frequencies: FrequentValues::new(MAX_TRACKED_VALUES),
+            numbers: Some(Numbers::default()),
+        }
+    }
+}
+
+pub fn process_csv_stats(
+    input: &str,
+    opts: &CsvReadOpts,
+    top: usize,
+) -> anyhow::Result<Vec<ColumnStats>> {
+    let (headers, mut reader) = csv_reader(get_data_reader(input)?, opts)?;
+    let mut columns: Vec<ColumnAccumulator> = headers
+        .iter()
+        .map(|_| ColumnAccumulator::default())
+        .collect();
+
+    for record in reader.records() {
+        let record = record?;
+        for (column, value) in columns.iter_mut().zip(record.iter()) {
+            column.observe(value);
+        }
+    }
+
+    Ok(headers
+        .iter()
+        .zip(columns)
+        .map(|(name, column)| column.finish(name, top))
+        .collect())
+}
+
+/// Render column statistics as a table, one row per column.
+pub fn render_stats(stats: &[ColumnStats]) -> String {
+    let headers: Vec<String> = [
+        "column", "type", "count", "nulls", "distinct", "min", "max", "mean", "median", "top",
+    ]
+    .iter()
+    .map(|h| h.to_string())
+    .collect();
+    let number = |n: Option<f64>| n.map_or(Value::Null, round);
+    // 估算值前加 ~ 标出
+    let mark = |s: &ColumnStats, stat: &str, value: Value| {
+        if s.approximate.iter().any(|a| a == stat) && !value.is_null() {
+            Value::from(format!("~{}", value))
+        } else {
+            value
+        }
+    };
+    let rows: Vec<Vec<Value>> = stats
+        .iter()
+        .map(|s| {
+            let top = s
+                .top
+                .iter()
+                .map(|v| format!("{} ({})", v.value, v.count))
+                .collect::<Vec<_>>()
+                .join(", ");
+            vec![
+                Value::from(s.column.as_str()),
+                Value::from(s.ty.as_str()),
+                Value::from(s.count),
+                Value::from(s.nulls),
+                mark(s, "distinct", Value::from(s.distinct)),
+                number(s.min),
+                number(s.max),
+                number(s.mean),
+                mark(s, "median", number(s.median)),
+                mark(s, "top", Value::from(top)),
+            ]
+        })
+        .collect();
+    render_table(&headers, &rows, 60, terminal_width())
+}
+
+fn round(n: f64) -> Value {
+    let n = (n * 1000.0).round() / 1000.0;
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        Value::from(n as i64)
+    } else {
+        Value::from(n)
+    }
+}
+
+impl ColumnAccumulator {
+    fn observe(&mut self, value: &str) {
+        if value.is_empty() {
+            self.nulls += 1;
+            return;
+        }
+        self.count += 1;
+        self.guess.observe(value);
+
+        let mut hasher = DefaultHasher::new();
+        value.hash(&mut hasher);
+        self.distinct.insert(hasher.finish());
+        self.frequencies.insert(value);
+
+        if matches!(
+            self.guess.column_type(),
+            ColumnType::Int | ColumnType::Float
+        ) {
+            if let (Some(numbers), Ok(n)) = (&mut self.numbers, value.parse()) {
+                numbers.insert(n);
+            }
+        } else {
+            self.numbers = None;
+        }
+    }
+
+    fn finish(self, name: &str, top: usize) -> ColumnStats {
+        let ty = self.guess.column_type();
+        let mut approximate = Vec::new();
+        let numbers = self
+            .numbers
+            .filter(|n| n.count > 0 && matches!(ty, ColumnType::Int | ColumnType::Float));
+        let (min, max, mean, median) = match numbers {
+            Some(numbers) => {
+                if numbers.median.is_some() {
+                    approximate.push("median".to_string());
+                }
+                (
+                    Some(numbers.min),
+                    Some(numbers.max),
+                    Some(numbers.sum / numbers.count as f64),
+                    numbers.median(),
+                )
+            }
+            None => (None, None, None, None),
+        };
+        let distinct = match &self.distinct {
+            Distinct::Exact(hashes) => hashes.len() as u64,
+            Distinct::Estimated(hll) => {
+                approximate.push("distinct".to_string());
+                hll.estimate()
+            }
+        };
+        if self.frequencies.is_approximate() {
+            approximate.push("top".to_string());
+        }
+
+        let mut frequencies: Vec<_> = self.frequencies.into_counts().into_iter().collect();
+        // 频次相同时按取值排序，保证输出稳定
+        frequencies.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        let top = frequencies
+            .into_iter()
+            .take(top)
+            .map(|(value, count)| ValueCount { value, count })
+            .collect();
+
+        ColumnStats {
+            column: name.to_string(),
+            ty: ty.to_string(),
+            count: self.count,
+            nulls: self.nulls,
+            distinct,
+            min,
+            max,
+            mean,
+            median,
+            top,
+            approximate,
+        }
+    }
+}
+
+impl Distinct {
+    fn insert(&mut self, hash: u64) {
+        match self {
+            Distinct::Exact(hashes) => {
+                hashes.insert(hash);
+                if hashes.len() > MAX_TRACKED_VALUES {
+                    let mut hll = HyperLogLog::new();
+                    hashes.drain().for_each(|h| hll.insert(h));
+                    *self = Distinct::Estimated(hll);
+                }
+            }
+            Distinct::Estimated(hll) => hll.insert(hash),
+        }
+    }
+}
+
+impl Numbers {
+    fn insert(&mut self, n: f64) {
+        if self.count == 0 {
+            (self.min, self.max) = (n, n);
+        }
+        self.count += 1;
+        self.sum += n;
+        self.min = self.min.min(n);
+        self.max = self.max.max(n);
+        match &mut self.median {
+            Some(median) => median.insert(n),
+            None => {
+                self.values.push(n);
+                if self.values.len() > MAX_TRACKED_VALUES {
+                    let mut median = P2Median::default();
+                    mem::take(&mut self.values)
+                        .into_iter()
+                        .for_each(|v| median.insert(v));
+                    self.median = Some(median);
+                }
+            }
+        }
+    }
+
+    fn median(mut self) -> Option<f64> {
+        if let Some(median) = &self.median {
+            return median.estimate();
+        }
+        self.values.sort_by(f64::total_cmp);
+        let len = self.values.len();
+        if len == 0 {
+            None
+        } else if len % 2 == 1 {
+            Some(self.values[len / 2])
+        } else {
+            Some((self.values[len / 2 - 1] + self.values[len / 2]) / 2.0)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_process_csv_stats() -> anyhow::Result<()> {
+        let stats = process_csv_stats("assets/juventus.csv", &CsvReadOpts::default(), 2)?;
+        assert_eq!(stats.len(), 5);
+
+        let nationality = &stats[3];
+        assert_eq!(nationality.ty, "string");
+        assert_eq!(nationality.count, 27);
+        assert_eq!(nationality.nulls, 0);
+        assert_eq!(nationality.min, None);
+        assert_eq!(
+            nationality.top[0],
+            ValueCount {
+                value: "Italy".into(),
+                count: 8
+            }
+        );
+
+        let number = &stats[4];
+        assert_eq!(number.ty, "int");
+        assert_eq!(number.distinct, 27);
+        assert_eq!(number.min, Some(1.0));
+        assert_eq!(number.max, Some(77.0));
+        Ok(())
+    }
+
+    #[test]
+    fn test_column_accumulator() {
+        let mut column = ColumnAccumulator::default();
+        for value in ["3", "1", "", "2", "10", "2"] {
+            column.observe(value);
+        }
+        let stats = column.finish("n", 1);
+        assert_eq!((stats.count, stats.nulls, stats.distinct), (5, 1, 4));
+        assert_eq!(stats.median, Some(2.0));
+        assert_eq!(stats.mean, Some(3.6));
+        assert_eq!(stats.top[0].value, "2");
+
+        let mut column = ColumnAccumulator::default();
+        for value in ["1", "2", "n/a"] {
+            column.observe(value);
+        }
+        let stats = column.finish("mixed", 3);
+        assert_eq!(stats.ty, "string");
+        assert_eq!(stats.mean, None);
+        assert!(stats.approximate.is_empty());
+    }
+
+    #[test]
+    fn test_column_accumulator_bounded() {
+        let mut column = ColumnAccumulator::default();
+        for i in 0..(MAX_TRACKED_VALUES as u64 * 2) {
+            column.observe(&i.to_string());
+        }
+        let stats = column.finish("id", 1);
+        assert_eq!(stats.approximate, vec!["median", "distinct", "top"]);
+        assert_eq!((stats.min, stats.max), (Some(0.0), Some(199_999.0)));
+        assert_eq!(stats.mean, Some(99_999.5));
+        assert!((stats.distinct as f64 - 200_000.0).abs() < 6_000.0);
+        assert!((stats.median.unwrap() - 100_000.0).abs() < 2_000.0);
+    }
+}
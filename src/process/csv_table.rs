@@ -0,0 +1,269 @@
+use crate::{
+    cli::{CsvReadOpts, CsvTransformOpts, CsvViewOpts},
+    get_data_reader, CsvRecords,
+};
+use serde_json::Value;
+use std::collections::VecDeque;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// 终端宽度未知时（例如输出被重定向）使用的宽度
+const DEFAULT_TERM_WIDTH: usize = 120;
+/// 按终端宽度收缩列时，每列至少保留的宽度
+const MIN_COLUMN_WIDTH: usize = 5;
+
+/// Render the selected rows of a CSV file as a table, prefixed with their row numbers.
+pub fn process_csv_show(
+    input: &str,
+    read: &CsvReadOpts,
+    transform: &CsvTransformOpts,
+    view: &CsvViewOpts,
+) -> anyhow::Result<String> {
+    // 表格按扁平的列展示，忽略 --unflatten
+    let transform = CsvTransformOpts {
+        unflatten: false,
+        ..transform.clone()
+    };
+    let records = CsvRecords::open(get_data_reader(input)?, read, &transform)?;
+    let mut headers = vec!["#".to_string()];
+    headers.extend(records.headers().map(String::from));
+
+    let (start, end) = match (&view.head, &view.rows) {
+        (Some(head), _) => (1, Some(*head)),
+        (None, Some(range)) => (range.start, range.end),
+        (None, None) => (1, None),
+    };
+    let mut rows = VecDeque::new();
+    for (i, record) in records.enumerate() {
+        let n = i + 1;
+        if end.is_some_and(|end| n > end) {
+            break;
+        }
+        let record = record?;
+        if n < start {
+            continue;
+        }
+        let mut row = vec![Value::from(n)];
+        row.extend(record.into_iter().map(|(_, v)| v));
+        rows.push_back(row);
+        // --tail 只保留最后 N 行
+        if view.tail.is_some_and(|tail| rows.len() > tail) {
+            rows.pop_front();
+        }
+    }
+
+    let rows: Vec<_> = rows.into();
+    Ok(render_table(
+        &headers,
+        &rows,
+        view.max_width,
+        terminal_width(),
+    ))
+}
+
+pub fn terminal_width() -> usize {
+    terminal_size::terminal_size()
+        .map(|(w, _)| w.0 as usize)
+        .or_else(|| std::env::var("COLUMNS").ok()?.parse().ok())
+        .unwrap_or(DEFAULT_TERM_WIDTH)
+}
+
+/// Draw a unicode boxed table. Cells longer than `max_width` are truncated, and the widest
+/// columns are shrunk further until the table fits in `total_width`.
+pub fn render_table(
+    headers: &[String],
+    rows: &[Vec<Value>],
+    max_width: usize,
+    total_width: usize,
+) -> String {
+    let headers: Vec<String> = headers.iter().map(|h| visible_text(h)).collect();
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| row.iter().map(cell_text).collect())
+        .collect();
+    let mut widths: Vec<usize> = headers
+        .iter()
+        .enumerate()
+        .map(|(i, h)| {
+            cells
+                .iter()
+                .filter_map(|row| row.get(i))
+                .map(|c| c.width())
+                .chain([h.width()])
+                .max()
+                .unwrap_or(0)
+                .min(max_width.max(1))
+        })
+        .collect();
+
+    // 每列左右各一个空格加一条竖线，再加最右侧的竖线
+    let overhead = widths.len() * 3 + 1;
+    while widths.iter().sum::<usize>() + overhead > total_width {
+        let Some(widest) = widths
+            .iter_mut()
+            .filter(|w| **w > MIN_COLUMN_WIDTH)
+            .max_by_key(|w| **w)
+        else {
+            break;
+        };
+        *widest -= 1;
+    }
+
+    let numeric: Vec<bool> = (0..headers.len())
+        .map(|i| {
+            rows.iter()
+                .filter_map(|row| row.get(i))
+                .all(|v| v.is_number() || v.is_null())
+        })
+        .collect();
+
+    let mut out = String::new();
+    out.push_str(&border(&widths, '┌', '┬', '┐'));
+    out.push_str(&line(&headers, &widths, &vec![false; widths.len()]));
+    out.push_str(&border(&widths, '├', '┼', '┤'));
+    for row in &cells {
+        out.push_str(&line(row, &widths, &numeric));
+    }
+    out.push_str(&border(&widths, '└', '┴', '┘'));
+    out
+}
+
+/// The text shown for a cell: strings without quotes, null as empty, control characters replaced.
+pub fn cell_text(value: &Value) -> String {
+    let text = match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        _ => value.to_string(),
+    };
+    visible_text(&text)
+}
+
+/// 换行和制表符换成空格，其余控制字符（如 ANSI 转义序列的 ESC）换成 �，避免改写终端
+fn visible_text(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\n' | '\r' | '\t' => ' ',
+            c if c.is_control() => char::REPLACEMENT_CHARACTER,
+            c => c,
+        })
+        .collect()
+}
+
+fn border(widths: &[usize], left: char, mid: char, right: char) -> String {
+    let parts: Vec<String> = widths.iter().map(|w| "─".repeat(w + 2)).collect();
+    format!("{}{}{}\n", left, parts.join(&mid.to_string()), right)
+}
+
+fn line(cells: &[String], widths: &[usize], right_align: &[bool]) -> String {
+    let mut out = String::from("│");
+    for (i, width) in widths.iter().enumerate() {
+        let text = truncate(cells.get(i).map_or("", |c| c.as_str()), *width);
+        let pad = " ".repeat(width - text.width());
+        if right_align[i] {
+            out.push_str(&format!(" {}{} │", pad, text));
+        } else {
+            out.push_str(&format!(" {}{} │", text, pad));
+        }
+    }
+    out.push('\n');
+    out
+}
+
+/// Cut `text` to at most `width` display columns, marking the cut with an ellipsis.
+fn truncate(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let mut out = String::new();
+    let mut used = 0;
+    for c in text.chars() {
+        let w = c.width().unwrap_or(0);
+        if used + w + 1 > width {
+            break;
+        }
+        out.push(c);
+        used += w;
+    }
+    out.push('…');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_table() {
+        let headers = vec!["name".to_string(), "age".to_string()];
+        let rows = vec![
+            vec![json!("Buffon"), json!(41)],
+            vec![json!("Dybala"), json!(5)],
+        ];
+        assert_eq!(
+            render_table(&headers, &rows, 40, 80),
+            "┌────────┬─────┐\n\
+             │ name   │ age │\n\
+             ├────────┼─────┤\n\
+             │ Buffon │  41 │\n\
+             │ Dybala │   5 │\n\
+             └────────┴─────┘\n"
+        );
+    }
+
+    #[test]
+    fn test_render_table_truncates() {
+        let headers = vec!["name".to_string()];
+        let rows = vec![vec![json!("Gianluigi Buffon")], vec![json!("梅西")]];
+        let table = render_table(&headers, &rows, 8, 80);
+        assert!(table.contains("│ Gianlui… │"));
+        assert!(table.contains("│ 梅西     │"));
+        // 终端太窄时收缩最宽的列
+        let table = render_table(&headers, &rows, 40, 10);
+        assert!(table.lines().all(|l| l.width() <= 10));
+    }
+
+    #[test]
+    fn test_cell_text_hides_control_characters() {
+        assert_eq!(cell_text(&json!("a\nb\tc")), "a b c");
+        assert_eq!(cell_text(&json!("\u{1b}[31mred\u{7}")), "�[31mred�");
+        let headers = vec!["\u{1b}]0;title".to_string()];
+        let table = render_table(&headers, &[], 40, 80);
+        assert!(!table.contains('\u{1b}'));
+    }
+
+    #[test]
+    fn test_process_csv_show_paging() -> anyhow::Result<()> {
+        let show = |view: CsvViewOpts| {
+            process_csv_show(
+                "assets/juventus.csv",
+                &CsvReadOpts::default(),
+                &CsvTransformOpts::default(),
+                &CsvViewOpts {
+                    max_width: 40,
+                    ..view
+                },
+            )
+        };
+        let table = show(CsvViewOpts {
+            head: Some(2),
+            ..Default::default()
+        })?;
+        assert!(table.contains("Wojciech Szczesny") && table.contains("Mattia Perin"));
+        assert_eq!(table.lines().count(), 6);
+
+        let table = show(CsvViewOpts {
+            tail: Some(1),
+            ..Default::default()
+        })?;
+        assert!(table.contains("│ 27 │"));
+        assert_eq!(table.lines().count(), 5);
+
+        let table = show(CsvViewOpts {
+            rows: Some("3..=4".parse()?),
+            ..Default::default()
+        })?;
+        assert!(table.contains("Gianluigi Buffon") && table.contains("Carlo Pinsoglio"));
+        assert_eq!(table.lines().count(), 6);
+        Ok(())
+    }
+}
@@ -0,0 +1,393 @@
+use super::csv_covert::convert_cell;
+use crate::{
+    cli::{ColumnType, CsvReadOpts},
+    csv_reader, get_data_reader,
+};
+use anyhow::Context;
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+use std::{collections::HashMap, fmt, fs};
+
+/// A column spec file: `{"columns": [{"name": "age", "type": "int", "required": true}]}`.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CsvSchema {
+    pub columns: Vec<ColumnRule>,
+    /// Whether columns not listed in the schema are allowed.
+    #[serde(default = "default_true")]
+    pub additional_columns: bool,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColumnRule {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub ty: Option<String>,
+    /// The column must exist, and unless `nullable` says otherwise none of its cells may be empty.
+    #[serde(default)]
+    pub required: bool,
+    /// Whether empty cells are allowed, by default only in columns that aren't `required`.
+    pub nullable: Option<bool>,
+    pub pattern: Option<String>,
+    #[serde(rename = "enum")]
+    pub allowed: Option<Vec<Value>>,
+    #[serde(default)]
+    pub unique: bool,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+/// A problem found in the file, `line` is the 1-based line number in the input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    pub line: u64,
+    pub column: String,
+    pub message: String,
+}
+
+/// A rule ready to be checked against cells, with its column position resolved.
+struct CompiledRule<'a> {
+    rule: &'a ColumnRule,
+    index: usize,
+    ty: Option<ColumnType>,
+    pattern: Option<Regex>,
+    allowed: Option<Vec<String>>,
+    /// 记录每个取值第一次出现的行号，用于唯一性检查
+    seen: HashMap<String, u64>,
+}
+
+/// Validate a CSV file against a schema, returning at most `max_errors` problems.
+pub fn process_csv_validate(
+    input: &str,
+    schema: &str,
+    opts: &CsvReadOpts,
+    max_errors: usize,
+) -> anyhow::Result<Vec<ValidationError>> {
+    let schema = load_schema(schema)?;
+    let (headers, mut reader) = csv_reader(get_data_reader(input)?, opts)?;
+    let mut errors = Vec::new();
+
+    let mut rules = Vec::new();
+    for rule in &schema.columns {
+        match headers.iter().position(|h| h == rule.name) {
+            Some(index) => rules.push(CompiledRule::new(rule, index)?),
+            None if rule.required => errors.push(ValidationError {
+                line: 1,
+                column: rule.name.clone(),
+                message: "required column is missing".to_string(),
+            }),
+            None => {}
+        }
+    }
+    if !schema.additional_columns {
+        for header in headers
+            .iter()
+            .filter(|h| !schema.columns.iter().any(|c| c.name == *h))
+        {
+            errors.push(ValidationError {
+                line: 1,
+                column: header.to_string(),
+                message: "column is not allowed by the schema".to_string(),
+            });
+        }
+    }
+
+    for record in reader.records() {
+        if errors.len() >= max_errors {
+            break;
+        }
+        let record = record?;
+        let line = record.position().map_or(0, |p| p.line());
+        for rule in rules.iter_mut() {
+            let value = record.get(rule.index).unwrap_or_default();
+            for message in rule.check(value, line) {
+                errors.push(ValidationError {
+                    line,
+                    column: rule.rule.name.clone(),
+                    message,
+                });
+            }
+        }
+    }
+
+    errors.truncate(max_errors);
+    Ok(errors)
+}
+
+/// Load a column spec, or translate a JSON Schema describing one row into a column spec.
+pub fn load_schema(path: &str) -> anyhow::Result<CsvSchema> {
+    let content = fs::read_to_string(path)?;
+    let value: Value = serde_json::from_str(&content)
+        .with_context(|| format!("{} is not a valid JSON file", path))?;
+    let schema: CsvSchema = if value.get("properties").is_some() {
+        from_json_schema(&value)?
+    } else {
+        serde_json::from_value(value)
+            .with_context(|| format!("{} is not a valid column spec", path))?
+    };
+    for rule in &schema.columns {
+        // min 和 max 只对数值列有意义，没有类型时不要悄悄忽略
+        let ty: Option<ColumnType> = rule.ty.as_deref().map(str::parse).transpose()?;
+        if (rule.min.is_some() || rule.max.is_some())
+            && !matches!(ty, Some(ColumnType::Int | ColumnType::Float))
+        {
+            anyhow::bail!(
+                "{}: column {} has min or max, which needs type int or float",
+                path,
+                rule.name
+            );
+        }
+    }
+    Ok(schema)
+}
+
+/// JSON Schema 中支持的子集：type、format: date、pattern、enum、minimum、maximum、
+/// required 与 additionalProperties，另外用 unique 标记需要唯一的列
+fn from_json_schema(schema: &Value) -> anyhow::Result<CsvSchema> {
+    let required: Vec<&str> = schema["required"]
+        .as_array()
+        .map(|r| r.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let properties = schema["properties"]
+        .as_object()
+        .context("properties must be an object")?;
+
+    let columns = properties
+        .iter()
+        .map(|(name, prop)| {
+            // "type": ["integer", "null"] 取非 null 的类型，并允许空单元格
+            let nullable = match &prop["type"] {
+                Value::String(t) => t == "null",
+                Value::Array(types) => types.iter().any(|t| t == "null"),
+                _ => true,
+            };
+            let ty = match &prop["type"] {
+                Value::String(t) => Some(t.as_str()),
+                Value::Array(types) => types
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .find(|t| *t != "null"),
+                _ => None,
+            };
+            let ty = match (ty, prop["format"].as_str()) {
+                (_, Some("date")) => Some("date"),
+                (Some("integer"), _) => Some("int"),
+                (Some("number"), _) => Some("float"),
+                (Some("boolean"), _) => Some("bool"),
+                (Some(_), _) => Some("string"),
+                (None, _) => None,
+            };
+            ColumnRule {
+                name: name.clone(),
+                ty: ty.map(String::from),
+                // JSON Schema 的 required 只要求列存在，空单元格由类型是否允许 null 决定
+                required: required.contains(&name.as_str()),
+                nullable: Some(nullable),
+                pattern: prop["pattern"].as_str().map(String::from),
+                allowed: prop["enum"].as_array().cloned(),
+                unique: prop["unique"].as_bool().unwrap_or_default(),
+                min: prop["minimum"].as_f64(),
+                max: prop["maximum"].as_f64(),
+            }
+        })
+        .collect();
+
+    Ok(CsvSchema {
+        columns,
+        additional_columns: schema["additionalProperties"].as_bool() != Some(false),
+    })
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl<'a> CompiledRule<'a> {
+    fn new(rule: &'a ColumnRule, index: usize) -> anyhow::Result<Self> {
+        let ty = rule.ty.as_deref().map(str::parse).transpose()?;
+        let pattern = match &rule.pattern {
+            // 与 JSON Schema 一致，pattern 不隐式锚定，需要整格匹配时自行加上 ^ 和 $
+            Some(p) => Some(
+                Regex::new(p)
+                    .with_context(|| format!("invalid pattern for column {}", rule.name))?,
+            ),
+            None => None,
+        };
+        let allowed = rule.allowed.as_ref().map(|values| {
+            values
+                .iter()
+                .map(|v| match v {
+                    Value::String(s) => s.clone(),
+                    _ => v.to_string(),
+                })
+                .collect()
+        });
+        Ok(Self {
+            rule,
+            index,
+            ty,
+            pattern,
+            allowed,
+            seen: HashMap::new(),
+        })
+    }
+
+    /// Check a cell against every part of the rule, returning all the problems found.
+    fn check(&mut self, value: &str, line: u64) -> Vec<String> {
+        let mut errors = Vec::new();
+        if value.is_empty() {
+            if !self.rule.nullable.unwrap_or(!self.rule.required) {
+                errors.push("value must not be empty".to_string());
+            }
+            return errors;
+        }
+
+        if let Some(ty) = self.ty {
+            match convert_cell(value, ty) {
+                Ok(converted) => {
+                    if let Some(n) = converted.as_f64() {
+                        if let Some(min) = self.rule.min.filter(|&min| n < min) {
+                            errors.push(format!("{} is less than {}", value, min));
+                        }
+                        if let Some(max) = self.rule.max.filter(|&max| n > max) {
+                            errors.push(format!("{} is greater than {}", value, max));
+                        }
+                    }
+                }
+                Err(_) => errors.push(format!("{:?} is not of type {}", value, ty)),
+            }
+        }
+        if let Some(pattern) = &self.pattern {
+            if !pattern.is_match(value) {
+                errors.push(format!("{:?} does not match {}", value, pattern.as_str()));
+            }
+        }
+        if let Some(allowed) = &self.allowed {
+            if !allowed.iter().any(|a| a == value) {
+                errors.push(format!("{:?} is not one of {}", value, allowed.join(", ")));
+            }
+        }
+        if self.rule.unique {
+            match self.seen.get(value) {
+                Some(first) => errors.push(format!("{:?} duplicates line {}", value, first)),
+                None => {
+                    self.seen.insert(value.to_string(), line);
+                }
+            }
+        }
+        errors
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "line {}: column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_column_spec() -> anyhow::Result<()> {
+        let errors = process_csv_validate(
+            "assets/juventus.csv",
+            "fixtures/juventus.schema.json",
+            &CsvReadOpts::default(),
+            100,
+        )?;
+        let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            errors,
+            vec![
+                "line 1: column Club: required column is missing",
+                "line 4: column Kit Number: 77 is greater than 50",
+                "line 26: column Position: \"Second Striker\" is not one of Goalkeeper, \
+                 Centre-Back, Left-Back, Right-Back, Defensive Midfield, Central Midfield, \
+                 Left Winger, Right Winger, Centre-Forward",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_json_schema() -> anyhow::Result<()> {
+        let errors = process_csv_validate(
+            "fixtures/players_invalid.csv",
+            "fixtures/players.schema.json",
+            &CsvReadOpts::default(),
+            100,
+        )?;
+        let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            errors,
+            vec![
+                "line 3: column age: \"thirty\" is not of type int",
+                "line 4: column name: value must not be empty",
+                "line 5: column id: \"1\" duplicates line 2",
+                "line 5: column email: \"dybala@\" does not match [^@]+@[^@]+",
+            ]
+        );
+
+        let errors = process_csv_validate(
+            "fixtures/players_invalid.csv",
+            "fixtures/players.schema.json",
+            &CsvReadOpts::default(),
+            2,
+        )?;
+        assert_eq!(errors.len(), 2);
+
+        // required 只要求列存在，允许 null 的类型可以为空
+        let dir = tempfile::tempdir()?;
+        let schema = dir.path().join("schema.json");
+        std::fs::write(
+            &schema,
+            r#"{"properties": {"age": {"type": ["integer", "null"]}, "email": {"type": "string"}},
+                "required": ["age"]}"#,
+        )?;
+        let errors = process_csv_validate(
+            "fixtures/players_invalid.csv",
+            schema.to_str().unwrap(),
+            &CsvReadOpts::default(),
+            100,
+        )?;
+        let errors: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            errors,
+            vec![
+                "line 3: column age: \"thirty\" is not of type int",
+                "line 4: column email: value must not be empty",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_validate_reports_every_rule() -> anyhow::Result<()> {
+        let dir = tempfile::tempdir()?;
+        let schema = dir.path().join("schema.json");
+        let rule = r#"{"name": "Position", "pattern": "^[A-Z]{2}$", "enum": ["GK"]}"#;
+        std::fs::write(&schema, format!(r#"{{"columns": [{}]}}"#, rule))?;
+        let errors = process_csv_validate(
+            "assets/juventus.csv",
+            schema.to_str().unwrap(),
+            &CsvReadOpts::default(),
+            2,
+        )?;
+        assert_eq!(errors[0].line, errors[1].line);
+        assert!(errors[0].message.contains("does not match"));
+        assert!(errors[1].message.contains("is not one of"));
+
+        std::fs::write(&schema, r#"{"columns": [{"name": "Name", "min": 1}]}"#)?;
+        let err = load_schema(schema.to_str().unwrap()).expect_err("min needs a type");
+        assert!(err.to_string().contains("needs type int or float"));
+        Ok(())
+    }
+}
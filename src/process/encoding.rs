@@ -0,0 +1,188 @@
+use crate::cli::InputEncoding;
+use chardetng::EncodingDetector;
+use encoding_rs::{Decoder, DecoderResult, Encoding, GBK, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+use encoding_rs_io::DecodeReaderBytesBuilder;
+use std::io::{self, Cursor, Read};
+
+/// 自动检测时读取的样本字节数
+const DETECT_SAMPLE_BYTES: u64 = 64 * 1024;
+/// 严格解码时每次读取的字节数
+const DECODE_BUFFER_BYTES: usize = 8 * 1024;
+
+/// Wrap `reader` so that it yields UTF-8, with any byte order mark removed.
+///
+/// A byte order mark always wins. Otherwise `auto` keeps valid UTF-8 as is and guesses the
+/// encoding of anything else from the first 64 KB, replacing bytes the guess can't decode
+/// with U+FFFD. An explicit encoding is strict: malformed input is an error.
+pub fn decode_reader(
+    reader: impl Read + 'static,
+    encoding: InputEncoding,
+) -> anyhow::Result<Box<dyn Read>> {
+    let encoding = match encoding {
+        InputEncoding::Auto => {
+            let mut reader = reader;
+            let mut sample = Vec::new();
+            reader
+                .by_ref()
+                .take(DETECT_SAMPLE_BYTES)
+                .read_to_end(&mut sample)?;
+            let encoding = detect_encoding(&sample);
+            return Ok(Box::new(
+                DecodeReaderBytesBuilder::new()
+                    .encoding(Some(encoding))
+                    .bom_override(true)
+                    .build(Cursor::new(sample).chain(reader)),
+            ));
+        }
+        InputEncoding::Utf8 => UTF_8,
+        InputEncoding::Gbk => GBK,
+        InputEncoding::Utf16le => UTF_16LE,
+        InputEncoding::Utf16be => UTF_16BE,
+        InputEncoding::Latin1 => WINDOWS_1252,
+    };
+    Ok(Box::new(StrictDecoder {
+        reader,
+        encoding,
+        decoder: encoding.new_decoder(),
+        input: vec![0; DECODE_BUFFER_BYTES],
+        start: 0,
+        end: 0,
+        offset: 0,
+        output: Vec::new(),
+        pos: 0,
+        eof: false,
+        done: false,
+    }))
+}
+
+/// Decodes to UTF-8, failing on the first malformed sequence instead of replacing it.
+struct StrictDecoder<R> {
+    reader: R,
+    encoding: &'static Encoding,
+    /// 新建的解码器会识别 BOM，BOM 优先于指定的编码
+    decoder: Decoder,
+    input: Vec<u8>,
+    start: usize,
+    end: usize,
+    /// 已解码的输入字节数，用于报告出错位置
+    offset: u64,
+    output: Vec<u8>,
+    pos: usize,
+    eof: bool,
+    done: bool,
+}
+
+impl<R: Read> Read for StrictDecoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.output.len() {
+            if self.done {
+                return Ok(0);
+            }
+            if self.start == self.end && !self.eof {
+                self.start = 0;
+                self.end = self.reader.read(&mut self.input)?;
+                self.eof = self.end == 0;
+            }
+            let input = &self.input[self.start..self.end];
+            let capacity = self
+                .decoder
+                .max_utf8_buffer_length_without_replacement(input.len())
+                .unwrap_or(input.len() * 3 + 16);
+            self.output.resize(capacity, 0);
+            let (result, read, written) =
+                self.decoder
+                    .decode_to_utf8_without_replacement(input, &mut self.output, self.eof);
+            self.start += read;
+            self.offset += read as u64;
+            self.output.truncate(written);
+            self.pos = 0;
+            match result {
+                DecoderResult::InputEmpty => self.done = self.eof,
+                DecoderResult::OutputFull => {}
+                DecoderResult::Malformed(len, extra) => {
+                    let at = self.offset - len as u64 - extra as u64;
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("input is not valid {} at byte {}", self.encoding.name(), at),
+                    ));
+                }
+            }
+        }
+        let n = buf.len().min(self.output.len() - self.pos);
+        buf[..n].copy_from_slice(&self.output[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Guess the encoding of the start of a file.
+pub fn detect_encoding(sample: &[u8]) -> &'static Encoding {
+    if let Some((encoding, _)) = Encoding::for_bom(sample) {
+        return encoding;
+    }
+    // 样本可能在多字节字符中间被截断，结尾不完整不算错误
+    match std::str::from_utf8(sample) {
+        Ok(_) => return UTF_8,
+        Err(e) if e.error_len().is_none() => return UTF_8,
+        Err(_) => {}
+    }
+    // 没有 BOM 的 UTF-16 中 ASCII 字符的高字节为 0
+    let zeros = |offset: usize| {
+        sample
+            .iter()
+            .skip(offset)
+            .step_by(2)
+            .filter(|b| **b == 0)
+            .count()
+    };
+    let half = sample.len() / 2;
+    if half > 0 && zeros(1) * 4 > half {
+        return UTF_16LE;
+    }
+    if half > 0 && zeros(0) * 4 > half {
+        return UTF_16BE;
+    }
+
+    let mut detector = EncodingDetector::new();
+    detector.feed(sample, sample.len() < DETECT_SAMPLE_BYTES as usize);
+    detector.guess(None, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(bytes: &'static [u8], encoding: InputEncoding) -> anyhow::Result<String> {
+        let mut content = String::new();
+        decode_reader(bytes, encoding)?.read_to_string(&mut content)?;
+        Ok(content)
+    }
+
+    #[test]
+    fn test_decode_reader() -> anyhow::Result<()> {
+        let gbk = include_bytes!("../../fixtures/players_gbk.csv");
+        let utf16 = include_bytes!("../../fixtures/players_utf16le.csv");
+        let expected = "姓名,位置,年龄\n布冯,门将,41\n迪巴拉,前锋,25\n";
+        assert_eq!(decode(gbk, InputEncoding::Gbk)?, expected);
+        assert_eq!(decode(gbk, InputEncoding::Auto)?, expected);
+        assert_eq!(decode(utf16, InputEncoding::Auto)?, expected);
+        // BOM 优先于指定的编码
+        assert_eq!(decode(utf16, InputEncoding::Gbk)?, expected);
+        assert_eq!(
+            decode(b"\xef\xbb\xbfname\n", InputEncoding::Auto)?,
+            "name\n"
+        );
+        assert_eq!(decode(b"caf\xe9\n", InputEncoding::Latin1)?, "café\n");
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_reader_strict() -> anyhow::Result<()> {
+        let err = decode(b"name\ncaf\xe9\n", InputEncoding::Utf8).expect_err("not UTF-8");
+        assert!(err.to_string().contains("UTF-8 at byte 8"), "{}", err);
+        assert!(decode(b"\xff\xfe\x00\xd8", InputEncoding::Utf16le).is_err());
+        // 自动检测仍然宽松
+        assert_eq!(decode(b"caf\xe9\n", InputEncoding::Auto)?, "café\n");
+        Ok(())
+    }
+}
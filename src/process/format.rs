@@ -0,0 +1,608 @@
+use crate::{
+    cli::{ArrayMode, CsvReadOpts, CsvTransformOpts, OutputFormat},
+    write_csv, CsvRecords,
+};
+use anyhow::Context;
+use rust_xlsxwriter::{Format, Workbook};
+use serde::Deserialize;
+use serde_json::Value;
+use std::io::{BufRead, Cursor, Write};
+
+use super::csv_table::cell_text;
+
+/// XLSX 列宽的上限（字符数）
+const MAX_XLSX_COLUMN_WIDTH: usize = 60;
+
+/// Serialize records one at a time so that output never has to be held in memory.
+pub trait RecordWriter {
+    fn write_record(&mut self, record: &Value) -> anyhow::Result<()>;
+    fn finish(self: Box<Self>) -> anyhow::Result<()>;
+}
+
+pub fn record_writer<'a>(
+    format: OutputFormat,
+    writer: impl Write + 'a,
+) -> Box<dyn RecordWriter + 'a> {
+    match format {
+        OutputFormat::Csv => Box::new(CsvWriter {
+            writer,
+            records: Vec::new(),
+        }),
+        OutputFormat::Json => Box::new(JsonArrayWriter { writer, count: 0 }),
+        OutputFormat::Ndjson => Box::new(NdjsonWriter { writer }),
+        OutputFormat::Yaml => Box::new(YamlWriter { writer, count: 0 }),
+        OutputFormat::Toml => Box::new(TomlWriter { writer, count: 0 }),
+        OutputFormat::Cbor => Box::new(CborWriter { writer }),
+        OutputFormat::Msgpack => Box::new(MsgpackWriter { writer }),
+        OutputFormat::Markdown => Box::new(MarkdownWriter {
+            writer,
+            table: Table::default(),
+        }),
+        OutputFormat::Html => Box::new(HtmlWriter {
+            writer,
+            table: Table::default(),
+        }),
+        OutputFormat::Xlsx => Box::new(XlsxWriter {
+            writer,
+            workbook: Workbook::new(),
+            columns: Vec::new(),
+            widths: Vec::new(),
+            row: 1,
+        }),
+    }
+}
+
+/// Read a list of records from a document in the given format.
+///
+/// A top-level array yields its items, and a table holding a single array of objects (such as
+/// the `records` wrapper we emit for TOML) yields that array. Anything else is a single record.
+pub fn read_records(mut reader: impl BufRead, format: OutputFormat) -> anyhow::Result<Vec<Value>> {
+    let records = match format {
+        OutputFormat::Csv => {
+            let mut content = Vec::new();
+            reader.read_to_end(&mut content)?;
+            CsvRecords::open(
+                Cursor::new(content),
+                &CsvReadOpts::default(),
+                &CsvTransformOpts::default(),
+            )?
+            .map(|r| r.map(Value::Object))
+            .collect::<anyhow::Result<_>>()?
+        }
+        OutputFormat::Json => unwrap_records(serde_json::from_reader(reader)?),
+        OutputFormat::Ndjson => {
+            let mut records = Vec::new();
+            for (i, line) in reader.lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let record = serde_json::from_str(&line)
+                    .map_err(|e| anyhow::anyhow!("line {}: {}", i + 1, e))?;
+                records.push(record);
+            }
+            records
+        }
+        OutputFormat::Yaml => {
+            // 支持以 --- 分隔的多文档 YAML
+            let mut records = Vec::new();
+            for document in serde_yaml::Deserializer::from_reader(reader) {
+                let value = Value::deserialize(document)?;
+                if !value.is_null() {
+                    records.extend(unwrap_records(value));
+                }
+            }
+            records
+        }
+        OutputFormat::Toml => {
+            let mut content = String::new();
+            reader.read_to_string(&mut content)?;
+            let value: toml::Table = toml::from_str(&content)?;
+            unwrap_records(serde_json::to_value(value)?)
+        }
+        OutputFormat::Cbor => {
+            let mut items = Vec::new();
+            while !reader.fill_buf()?.is_empty() {
+                items.push(ciborium::from_reader(&mut reader)?);
+            }
+            unwrap_sequence(items)
+        }
+        OutputFormat::Msgpack => {
+            let mut items = Vec::new();
+            while !reader.fill_buf()?.is_empty() {
+                items.push(rmp_serde::from_read(&mut reader)?);
+            }
+            unwrap_sequence(items)
+        }
+        OutputFormat::Markdown | OutputFormat::Html | OutputFormat::Xlsx => {
+            anyhow::bail!("{} can only be written, not read", format)
+        }
+    };
+    Ok(records)
+}
+
+/// 二进制格式可能是单个数组文档，也可能是逐条写出的记录序列
+fn unwrap_sequence(mut items: Vec<Value>) -> Vec<Value> {
+    match items.len() {
+        1 => unwrap_records(items.remove(0)),
+        _ => items,
+    }
+}
+
+/// Unwrap a document into its records, see `read_records`.
+pub fn unwrap_records(value: Value) -> Vec<Value> {
+    match value {
+        Value::Array(items) => items,
+        Value::Object(map)
+            if map.len() == 1
+                && map
+                    .values()
+                    .all(|v| v.as_array().is_some_and(|a| a.iter().all(Value::is_object))) =>
+        {
+            match map.into_iter().next() {
+                Some((_, Value::Array(items))) => items,
+                _ => unreachable!("checked above"),
+            }
+        }
+        _ => vec![value],
+    }
+}
+
+/// CSV with the union of all records' columns as its header, written out when finished.
+struct CsvWriter<W> {
+    writer: W,
+    records: Vec<Value>,
+}
+
+/// A pretty printed JSON array, written element by element.
+struct JsonArrayWriter<W> {
+    writer: W,
+    count: usize,
+}
+
+/// One compact JSON object per line.
+struct NdjsonWriter<W> {
+    writer: W,
+}
+
+/// A YAML sequence, one `- ` item per record.
+struct YamlWriter<W> {
+    writer: W,
+    count: usize,
+}
+
+/// An array of tables named `records`, one `[[records]]` block per record.
+struct TomlWriter<W> {
+    writer: W,
+    count: usize,
+}
+
+/// A CBOR sequence (RFC 8742): one encoded item per record, back to back.
+struct CborWriter<W> {
+    writer: W,
+}
+
+/// A MessagePack stream: one encoded map per record, back to back.
+struct MsgpackWriter<W> {
+    writer: W,
+}
+
+/// A GitHub flavored Markdown table, written out when finished.
+struct MarkdownWriter<W> {
+    writer: W,
+    table: Table,
+}
+
+/// A standalone HTML page holding a styled table, written out when finished.
+struct HtmlWriter<W> {
+    writer: W,
+    table: Table,
+}
+
+/// Records buffered for a table whose header is the union of all their columns.
+#[derive(Default)]
+struct Table {
+    columns: Vec<String>,
+    records: Vec<Value>,
+}
+
+/// An XLSX workbook with a bold header row, written out when finished.
+struct XlsxWriter<W> {
+    writer: W,
+    workbook: Workbook,
+    columns: Vec<String>,
+    /// 每列内容的最大宽度，用于设置列宽
+    widths: Vec<usize>,
+    row: u32,
+}
+
+const HTML_HEAD: &str = "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<style>
+table { border-collapse: collapse; font-family: sans-serif; font-size: 14px; }
+th, td { border: 1px solid #d0d7de; padding: 6px 12px; }
+th { background: #f6f8fa; text-align: left; }
+tr:nth-child(even) td { background: #fafbfc; }
+td.number { text-align: right; }
+</style>
+</head>
+<body>
+<table>
+";
+
+/// 把记录中新出现的列按顺序加入列名
+fn add_columns(columns: &mut Vec<String>, record: &Value) {
+    for key in record.as_object().into_iter().flat_map(|map| map.keys()) {
+        if !columns.contains(key) {
+            columns.push(key.clone());
+        }
+    }
+}
+
+impl Table {
+    fn push(&mut self, record: &Value) {
+        add_columns(&mut self.columns, record);
+        self.records.push(record.clone());
+    }
+}
+
+fn markdown_escape(text: &str) -> String {
+    // 先转义反斜杠，否则单元格末尾的 `\` 会把后面的 `|` 变成转义字符
+    text.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace("\r\n", "<br>")
+        .replace(['\n', '\r'], "<br>")
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl<W: Write> RecordWriter for MarkdownWriter<W> {
+    fn write_record(&mut self, record: &Value) -> anyhow::Result<()> {
+        self.table.push(record);
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> anyhow::Result<()> {
+        let Table { columns, records } = std::mem::take(&mut self.table);
+        if !columns.is_empty() {
+            let header: Vec<String> = columns.iter().map(|c| markdown_escape(c)).collect();
+            writeln!(self.writer, "| {} |", header.join(" | "))?;
+            writeln!(self.writer, "|{}", " --- |".repeat(columns.len()))?;
+        }
+        for record in &records {
+            let cells: Vec<String> = columns
+                .iter()
+                .map(|c| markdown_escape(&cell_text(&record[c])))
+                .collect();
+            writeln!(self.writer, "| {} |", cells.join(" | "))?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write> RecordWriter for HtmlWriter<W> {
+    fn write_record(&mut self, record: &Value) -> anyhow::Result<()> {
+        self.table.push(record);
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> anyhow::Result<()> {
+        let Table { columns, records } = std::mem::take(&mut self.table);
+        self.writer.write_all(HTML_HEAD.as_bytes())?;
+        if !columns.is_empty() {
+            write!(self.writer, "<thead>\n<tr>")?;
+            for column in &columns {
+                write!(self.writer, "<th>{}</th>", html_escape(column))?;
+            }
+            write!(self.writer, "</tr>\n</thead>\n")?;
+        }
+        self.writer.write_all(b"<tbody>\n")?;
+        for record in &records {
+            write!(self.writer, "<tr>")?;
+            for column in &columns {
+                let value = &record[column];
+                let class = if value.is_number() {
+                    " class=\"number\""
+                } else {
+                    ""
+                };
+                write!(
+                    self.writer,
+                    "<td{}>{}</td>",
+                    class,
+                    html_escape(&cell_text(value))
+                )?;
+            }
+            writeln!(self.writer, "</tr>")?;
+        }
+        self.writer
+            .write_all(b"</tbody>\n</table>\n</body>\n</html>\n")?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+/// XLSX 的列号是 u16，工作表最多 16384 列
+fn xlsx_column(i: usize) -> anyhow::Result<u16> {
+    u16::try_from(i)
+        .ok()
+        .filter(|&col| col < 16_384)
+        .with_context(|| format!("XLSX holds at most 16384 columns, but there are {}", i + 1))
+}
+
+impl<W: Write> RecordWriter for XlsxWriter<W> {
+    fn write_record(&mut self, record: &Value) -> anyhow::Result<()> {
+        let sheet = match self.workbook.worksheets_mut().first_mut() {
+            Some(sheet) => sheet,
+            None => self.workbook.add_worksheet(),
+        };
+        // 表头在结束时按所有记录的列写入
+        add_columns(&mut self.columns, record);
+        self.widths.resize(self.columns.len(), 0);
+        for (i, column) in self.columns.iter().enumerate() {
+            let col = xlsx_column(i)?;
+            let value = &record[column];
+            match value {
+                Value::Null => {}
+                Value::Bool(b) => {
+                    sheet.write_boolean(self.row, col, *b)?;
+                }
+                Value::Number(n) => {
+                    sheet.write_number(self.row, col, n.as_f64().unwrap_or_default())?;
+                }
+                Value::String(s) => {
+                    sheet.write_string(self.row, col, s)?;
+                }
+                _ => {
+                    sheet.write_string(self.row, col, value.to_string())?;
+                }
+            }
+            self.widths[i] = self.widths[i].max(cell_text(value).chars().count());
+        }
+        self.row += 1;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> anyhow::Result<()> {
+        let widths = std::mem::take(&mut self.widths);
+        let sheet = match self.workbook.worksheets_mut().first_mut() {
+            Some(sheet) => sheet,
+            None => self.workbook.add_worksheet(),
+        };
+        if !self.columns.is_empty() {
+            let bold = Format::new().set_bold();
+            for (i, column) in self.columns.iter().enumerate() {
+                sheet.write_string_with_format(0, xlsx_column(i)?, column, &bold)?;
+            }
+            sheet.set_freeze_panes(1, 0)?;
+        }
+        for (i, (width, column)) in widths.into_iter().zip(&self.columns).enumerate() {
+            let width = (width.max(column.chars().count()) + 2).min(MAX_XLSX_COLUMN_WIDTH);
+            sheet.set_column_width(xlsx_column(i)?, width as f64)?;
+        }
+        let buffer = self.workbook.save_to_buffer()?;
+        self.writer.write_all(&buffer)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write> RecordWriter for CsvWriter<W> {
+    fn write_record(&mut self, record: &Value) -> anyhow::Result<()> {
+        self.records.push(record.clone());
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> anyhow::Result<()> {
+        write_csv(
+            &self.records,
+            &mut self.writer,
+            &CsvReadOpts::default(),
+            ArrayMode::Join,
+            ";",
+        )
+    }
+}
+
+impl<W: Write> RecordWriter for JsonArrayWriter<W> {
+    fn write_record(&mut self, record: &Value) -> anyhow::Result<()> {
+        let sep = if self.count == 0 { "[\n" } else { ",\n" };
+        self.writer.write_all(sep.as_bytes())?;
+        let content = serde_json::to_string_pretty(record)?;
+        for (i, line) in content.lines().enumerate() {
+            if i > 0 {
+                self.writer.write_all(b"\n")?;
+            }
+            write!(self.writer, "  {}", line)?;
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> anyhow::Result<()> {
+        let end = if self.count == 0 { "[]" } else { "\n]" };
+        self.writer.write_all(end.as_bytes())?;
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write> RecordWriter for NdjsonWriter<W> {
+    fn write_record(&mut self, record: &Value) -> anyhow::Result<()> {
+        serde_json::to_writer(&mut self.writer, record)?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> anyhow::Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write> RecordWriter for YamlWriter<W> {
+    fn write_record(&mut self, record: &Value) -> anyhow::Result<()> {
+        // 单元素序列的输出拼接起来就是完整的序列
+        let content = serde_yaml::to_string(std::slice::from_ref(record))?;
+        self.writer.write_all(content.as_bytes())?;
+        self.count += 1;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> anyhow::Result<()> {
+        if self.count == 0 {
+            self.writer.write_all(b"[]\n")?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write> RecordWriter for TomlWriter<W> {
+    fn write_record(&mut self, record: &Value) -> anyhow::Result<()> {
+        // 将记录包装在一个对象中，使其符合 TOML 格式要求
+        let wrapper = serde_json::json!({ "records": [record] });
+        if self.count > 0 {
+            self.writer.write_all(b"\n")?;
+        }
+        self.writer
+            .write_all(toml::to_string_pretty(&wrapper)?.as_bytes())?;
+        self.count += 1;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> anyhow::Result<()> {
+        if self.count == 0 {
+            self.writer.write_all(b"records = []\n")?;
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write> RecordWriter for CborWriter<W> {
+    fn write_record(&mut self, record: &Value) -> anyhow::Result<()> {
+        ciborium::into_writer(record, &mut self.writer)?;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> anyhow::Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+impl<W: Write> RecordWriter for MsgpackWriter<W> {
+    fn write_record(&mut self, record: &Value) -> anyhow::Result<()> {
+        rmp_serde::encode::write(&mut self.writer, record)?;
+        Ok(())
+    }
+
+    fn finish(mut self: Box<Self>) -> anyhow::Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn write_bytes(format: OutputFormat, records: &[Value]) -> anyhow::Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        let mut writer = record_writer(format, &mut buf);
+        for record in records {
+            writer.write_record(record)?;
+        }
+        writer.finish()?;
+        Ok(buf)
+    }
+
+    fn write_all(format: OutputFormat, records: &[Value]) -> anyhow::Result<String> {
+        Ok(String::from_utf8(write_bytes(format, records)?)?)
+    }
+
+    #[test]
+    fn test_streamed_output_matches_whole_document() -> anyhow::Result<()> {
+        let records = vec![
+            json!({"name": "Buffon", "age": 41}),
+            json!({"name": "Dybala", "age": 25}),
+        ];
+        assert_eq!(
+            write_all(OutputFormat::Json, &records)?,
+            serde_json::to_string_pretty(&records)?
+        );
+        assert_eq!(
+            write_all(OutputFormat::Yaml, &records)?,
+            serde_yaml::to_string(&records)?
+        );
+        let toml: toml::Value = toml::from_str(&write_all(OutputFormat::Toml, &records)?)?;
+        assert_eq!(toml["records"].as_array().map(|a| a.len()), Some(2));
+        assert_eq!(
+            write_all(OutputFormat::Ndjson, &records)?,
+            "{\"name\":\"Buffon\",\"age\":41}\n{\"name\":\"Dybala\",\"age\":25}\n"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_read_records_round_trip() -> anyhow::Result<()> {
+        let records = vec![
+            json!({"name": "Buffon", "age": 41}),
+            json!({"name": "Dybala", "age": 25}),
+        ];
+        for format in [
+            OutputFormat::Csv,
+            OutputFormat::Json,
+            OutputFormat::Ndjson,
+            OutputFormat::Yaml,
+            OutputFormat::Toml,
+            OutputFormat::Cbor,
+            OutputFormat::Msgpack,
+        ] {
+            let content = write_bytes(format, &records)?;
+            assert_eq!(read_records(content.as_slice(), format)?, records);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_table_outputs() -> anyhow::Result<()> {
+        let records = vec![
+            json!({"name": "Buffon | GK", "age": 41}),
+            json!({"name": "<Dybala>", "age": null, "club": "Juventus\\"}),
+        ];
+        assert_eq!(
+            write_all(OutputFormat::Markdown, &records)?,
+            "| name | age | club |\n| --- | --- | --- |\n| Buffon \\| GK | 41 |  |\n\
+             | <Dybala> |  | Juventus\\\\ |\n"
+        );
+        let html = write_all(OutputFormat::Html, &records)?;
+        assert!(html.contains("<tr><th>name</th><th>age</th><th>club</th></tr>"));
+        assert!(html.contains("<tr><td>&lt;Dybala&gt;</td><td></td><td>Juventus\\</td></tr>"));
+        assert!(html.ends_with("</table>\n</body>\n</html>\n"));
+
+        // XLSX 是 zip 文件
+        let xlsx = write_bytes(OutputFormat::Xlsx, &records)?;
+        assert!(xlsx.starts_with(b"PK"));
+        assert!(read_records(xlsx.as_slice(), OutputFormat::Xlsx).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_empty_output_is_valid() -> anyhow::Result<()> {
+        assert_eq!(write_all(OutputFormat::Json, &[])?, "[]");
+        assert_eq!(write_all(OutputFormat::Yaml, &[])?, "[]\n");
+        assert_eq!(write_all(OutputFormat::Ndjson, &[])?, "");
+        Ok(())
+    }
+}
@@ -1,43 +1,41 @@
+use crate::{
+    cli::OutputFormat, record_writer, CharClass, CharsetOpts, PasswordFormat, RecordWriter,
+};
 use rand::seq::SliceRandom;
+use serde::Serialize;
+use std::{collections::HashSet, fs, io::Write};
+use zxcvbn::zxcvbn;
 
-const UPPER: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
-const LOWER: &[u8] = b"abcdefghijklmnopqrstuvwxyz";
-const NUMBER: &[u8] = b"0123456789";
-const SYMBOL: &[u8] = b"!@#$%^&*_";
-
-pub fn process_genpass(
-    length: u8,
-    no_uppercase: bool,
-    no_lowercase: bool,
-    no_numbers: bool,
-    no_symbols: bool,
-) -> anyhow::Result<String> {
-    if no_uppercase && no_lowercase && no_numbers && no_symbols {
-        anyhow::bail!("At least one character type must be selected");
+const UPPER: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
+const LOWER: &str = "abcdefghijklmnopqrstuvwxyz";
+const NUMBER: &str = "0123456789";
+const SYMBOL: &str = "!@#$%^&*_";
+/// 容易看错的字符：0/O、1/l/I
+const SIMILAR: &str = "0O1lI";
+/// `genpass` 未指定长度时的默认长度
+pub const DEFAULT_LENGTH: usize = 16;
+
+pub fn process_genpass(length: usize, opts: &CharsetOpts) -> anyhow::Result<String> {
+    let classes = char_classes(opts)?;
+    if length < classes.len() {
+        anyhow::bail!(
+            "Length {} is too short to include all {} character types",
+            length,
+            classes.len()
+        );
     }
 
     let mut rng = rand::thread_rng();
     let mut password = Vec::new();
     let mut chars = Vec::new();
 
-    if !no_uppercase {
-        chars.extend_from_slice(UPPER);
-        password.push(*UPPER.choose(&mut rng).expect("UPPER won't be empty"));
-    }
-    if !no_lowercase {
-        chars.extend_from_slice(LOWER);
-        password.push(*LOWER.choose(&mut rng).expect("LOWER won't be empty"));
-    }
-    if !no_numbers {
-        chars.extend_from_slice(NUMBER);
-        password.push(*NUMBER.choose(&mut rng).expect("NUMBER won't be empty"));
-    }
-    if !no_symbols {
-        chars.extend_from_slice(SYMBOL);
-        password.push(*SYMBOL.choose(&mut rng).expect("SYMBOL won't be empty"));
+    // 每种启用的字符类至少出现一次
+    for (_, set) in &classes {
+        chars.extend_from_slice(set);
+        password.push(*set.choose(&mut rng).expect("set won't be empty"));
     }
 
-    for _ in 0..(length - password.len() as u8) {
+    for _ in 0..(length - password.len()) {
         let c = chars
             .choose(&mut rng)
             .expect("chars won't be empty in this context.");
@@ -46,7 +44,270 @@ pub fn process_genpass(
 
     password.shuffle(&mut rng);
 
-    let password = String::from_utf8(password)?;
+    Ok(password.into_iter().collect())
+}
+
+/// A generated password with the numbers `genpass --output-format` reports.
+#[derive(Debug, Serialize)]
+pub struct GeneratedPassword {
+    pub password: String,
+    pub length: usize,
+    /// Entropy in bits, rounded to two decimals.
+    pub entropy: f64,
+    /// zxcvbn score from 0 to 4.
+    pub score: u8,
+}
+
+impl GeneratedPassword {
+    /// Score the password with zxcvbn, only worth doing when the numbers are printed.
+    pub fn new(password: String, entropy: f64) -> anyhow::Result<Self> {
+        let score = zxcvbn(&password, &[])?.score();
+        Ok(Self {
+            length: password.chars().count(),
+            password,
+            entropy: (entropy * 100.0).round() / 100.0,
+            score,
+        })
+    }
+}
+
+/// Writes generated passwords one at a time, so `--count` never has to hold them in memory.
+pub enum PasswordWriter<'a> {
+    Json(Box<dyn RecordWriter + 'a>),
+    Csv(Box<csv::Writer<Box<dyn Write + 'a>>>),
+}
 
-    Ok(password)
+impl<'a> PasswordWriter<'a> {
+    pub fn new(format: PasswordFormat, writer: impl Write + 'a) -> Self {
+        match format {
+            PasswordFormat::Json => Self::Json(record_writer(OutputFormat::Json, writer)),
+            PasswordFormat::Csv => Self::Csv(Box::new(csv::Writer::from_writer(Box::new(writer)))),
+        }
+    }
+
+    pub fn write(&mut self, password: &GeneratedPassword) -> anyhow::Result<()> {
+        match self {
+            Self::Json(writer) => writer.write_record(&serde_json::to_value(password)?),
+            Self::Csv(writer) => Ok(writer.serialize(password)?),
+        }
+    }
+
+    pub fn finish(self) -> anyhow::Result<()> {
+        match self {
+            Self::Json(writer) => writer.finish(),
+            Self::Csv(mut writer) => Ok(writer.flush()?),
+        }
+    }
+}
+
+/// Size of the character pool `process_genpass` draws from.
+pub fn charset_size(opts: &CharsetOpts) -> anyhow::Result<usize> {
+    Ok(char_classes(opts)?.iter().map(|(_, set)| set.len()).sum())
+}
+
+/// 按选项得到启用的字符类，已去掉排除的字符
+pub(super) fn char_classes(opts: &CharsetOpts) -> anyhow::Result<Vec<(CharClass, Vec<char>)>> {
+    let enabled = if opts.charset.is_empty() {
+        [
+            (CharClass::Upper, opts.no_uppercase),
+            (CharClass::Lower, opts.no_lowercase),
+            (CharClass::Number, opts.no_numbers),
+            (CharClass::Symbol, opts.no_symbols),
+        ]
+        .into_iter()
+        .filter(|(_, disabled)| !disabled)
+        .map(|(class, _)| class)
+        .collect()
+    } else {
+        // `--charset lower,lower` 只算一次，否则第二个会因为字符都已用过而报错
+        let mut enabled = Vec::new();
+        for class in &opts.charset {
+            if !enabled.contains(class) {
+                enabled.push(*class);
+            }
+        }
+        enabled
+    };
+    if enabled.is_empty() {
+        anyhow::bail!("At least one character type must be selected");
+    }
+
+    let mut excluded: HashSet<char> = opts.exclude.as_deref().unwrap_or("").chars().collect();
+    if opts.exclude_similar {
+        excluded.extend(SIMILAR.chars());
+    }
+    // 同一个字符只归入一个字符类，避免重复计入字符池
+    let mut seen = HashSet::new();
+    let mut classes = Vec::new();
+    for class in enabled {
+        let source = match class {
+            CharClass::Upper => UPPER,
+            CharClass::Lower => LOWER,
+            CharClass::Number => NUMBER,
+            CharClass::Symbol => opts.symbols.as_deref().unwrap_or(SYMBOL),
+        };
+        let set: Vec<char> = source
+            .chars()
+            .filter(|c| !c.is_whitespace() && !excluded.contains(c) && seen.insert(*c))
+            .collect();
+        if set.is_empty() {
+            anyhow::bail!("No {} characters are left after exclusions", class);
+        }
+        classes.push((class, set));
+    }
+    Ok(classes)
+}
+
+/// Generate a passphrase of `words` random words picked from `wordlist`.
+pub fn process_genphrase(
+    words: usize,
+    separator: &str,
+    capitalize: bool,
+    wordlist: &[String],
+) -> anyhow::Result<String> {
+    if words == 0 {
+        anyhow::bail!("A passphrase needs at least one word");
+    }
+    let mut rng = rand::thread_rng();
+    let picked = (0..words)
+        .map(|_| {
+            let word = wordlist
+                .choose(&mut rng)
+                .expect("wordlist won't be empty in this context.");
+            if capitalize {
+                let mut chars = word.chars();
+                chars
+                    .next()
+                    .map(|c| c.to_uppercase().chain(chars).collect())
+                    .unwrap_or_default()
+            } else {
+                word.clone()
+            }
+        })
+        .collect::<Vec<String>>();
+    Ok(picked.join(separator))
+}
+
+/// Load a passphrase wordlist from `path`, such as the EFF large wordlist.
+///
+/// Lines in the EFF dice format (`11111\tabacus`) are accepted as well as one word per line.
+pub fn load_wordlist(path: &str) -> anyhow::Result<Vec<String>> {
+    let content = fs::read_to_string(path)?;
+    // 重复的词会让熵的估算偏高，去重后再用
+    let mut seen = HashSet::new();
+    let words: Vec<String> = content
+        .lines()
+        .filter_map(|line| line.split_whitespace().last())
+        .filter(|word| !word.starts_with('#') && seen.insert(word.to_string()))
+        .map(str::to_string)
+        .collect();
+    if words.len() < 2 {
+        anyhow::bail!("Wordlist needs at least 2 distinct words");
+    }
+    Ok(words)
+}
+
+/// Entropy in bits of `picks` independent uniform choices out of `choices`.
+pub fn entropy_bits(choices: usize, picks: usize) -> f64 {
+    picks as f64 * (choices as f64).log2()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_genpass_charset() -> anyhow::Result<()> {
+        let opts = CharsetOpts {
+            charset: vec![CharClass::Lower, CharClass::Number, CharClass::Symbol],
+            symbols: Some("-+".to_string()),
+            exclude_similar: true,
+            exclude: Some("abc".to_string()),
+            ..Default::default()
+        };
+        for _ in 0..20 {
+            let pwd = process_genpass(8, &opts)?;
+            assert_eq!(pwd.chars().count(), 8);
+            assert!(pwd.chars().any(|c| c.is_ascii_lowercase()));
+            assert!(pwd.chars().any(|c| c.is_ascii_digit()));
+            assert!(pwd.chars().any(|c| c == '-' || c == '+'));
+            assert!(!pwd.contains(|c: char| "abc01lIO".contains(c) || c.is_uppercase()));
+        }
+        // 26 - 3 - 1 个小写字母，10 - 2 个数字，2 个符号
+        assert_eq!(charset_size(&opts)?, 22 + 8 + 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_passwords() -> anyhow::Result<()> {
+        let passwords = (0..3)
+            .map(|_| {
+                let pwd = process_genpass(300, &CharsetOpts::default())?;
+                GeneratedPassword::new(pwd, entropy_bits(71, 300))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let write_all = |format| -> anyhow::Result<Vec<u8>> {
+            let mut buf = Vec::new();
+            let mut writer = PasswordWriter::new(format, &mut buf);
+            for password in &passwords {
+                writer.write(password)?;
+            }
+            writer.finish()?;
+            Ok(buf)
+        };
+        let csv = write_all(PasswordFormat::Csv)?;
+        let csv = String::from_utf8(csv)?;
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[0], "password,length,entropy,score");
+        assert!(lines[1].ends_with(",300,1844.92,4"));
+
+        let json: serde_json::Value = serde_json::from_slice(&write_all(PasswordFormat::Json)?)?;
+        assert_eq!(json[2]["length"], 300);
+        Ok(())
+    }
+
+    #[test]
+    fn test_charset_duplicates() -> anyhow::Result<()> {
+        let opts = CharsetOpts {
+            charset: vec![CharClass::Lower, CharClass::Lower],
+            ..Default::default()
+        };
+        assert_eq!(char_classes(&opts)?.len(), 1);
+        assert_eq!(charset_size(&opts)?, 26);
+        Ok(())
+    }
+
+    #[test]
+    fn test_genpass_invalid_charset() {
+        let opts = CharsetOpts {
+            charset: vec![CharClass::Number],
+            exclude: Some("0123456789".to_string()),
+            ..Default::default()
+        };
+        assert!(process_genpass(16, &opts).is_err());
+        assert!(process_genpass(3, &CharsetOpts::default()).is_err());
+    }
+
+    #[test]
+    fn test_genphrase() -> anyhow::Result<()> {
+        let wordlist: Vec<String> = (0..7776).map(|i| format!("word{}", i)).collect();
+        let phrase = process_genphrase(6, "-", true, &wordlist)?;
+        let words: Vec<&str> = phrase.split('-').collect();
+        assert_eq!(words.len(), 6);
+        assert!(words.iter().all(|w| w.starts_with(char::is_uppercase)));
+        assert!((entropy_bits(wordlist.len(), 6) - 77.5).abs() < 0.1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_eff_wordlist() -> anyhow::Result<()> {
+        let path = std::env::temp_dir().join("rcli_test_wordlist.txt");
+        fs::write(&path, "11111\tabacus\n11112\tabdomen\n11113\tabacus\n")?;
+        let wordlist = load_wordlist(path.to_str().unwrap())?;
+        assert_eq!(wordlist, vec!["abacus", "abdomen"]);
+        fs::write(&path, "only\n")?;
+        assert!(load_wordlist(path.to_str().unwrap()).is_err());
+        Ok(())
+    }
 }
@@ -0,0 +1,246 @@
+use crate::{CharClass, CharsetOpts};
+use anyhow::Context;
+use serde::Deserialize;
+use std::fs;
+
+use super::gen_pass::{char_classes, charset_size, entropy_bits, process_genpass, DEFAULT_LENGTH};
+
+/// 随机生成时最多尝试的次数，超过后认为策略过于严格
+const MAX_ATTEMPTS: usize = 10_000;
+/// 估算熵时抽样的候选密码个数
+const ENTROPY_SAMPLES: usize = 2_000;
+
+/// Constraints a generated password must satisfy, loaded from a TOML policy file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PasswordPolicy {
+    pub min_length: Option<usize>,
+    pub max_length: Option<usize>,
+    /// Character types that must each appear at least once.
+    #[serde(default)]
+    pub require: Vec<CharClass>,
+    /// Symbols the target system accepts, used unless `--symbols` is given.
+    pub symbols: Option<String>,
+    /// Substrings that must not appear, compared case-insensitively.
+    #[serde(default)]
+    pub forbidden: Vec<String>,
+    /// Longest allowed run of the same character, e.g. 2 rejects `aaa`.
+    pub max_repeat: Option<usize>,
+    #[serde(default)]
+    pub start_with_letter: bool,
+}
+
+pub fn load_policy(path: &str) -> anyhow::Result<PasswordPolicy> {
+    let content = fs::read_to_string(path)?;
+    toml::from_str(&content).with_context(|| format!("{} is not a valid password policy", path))
+}
+
+impl PasswordPolicy {
+    /// Check an explicit `length` against the policy, or pick the allowed length nearest the
+    /// default when none is given.
+    pub fn length(&self, length: Option<usize>) -> anyhow::Result<usize> {
+        let Some(length) = length else {
+            let length = DEFAULT_LENGTH.max(self.min_length.unwrap_or(0));
+            return Ok(self.max_length.map_or(length, |max| length.min(max)));
+        };
+        if let Some(min) = self.min_length.filter(|min| length < *min) {
+            anyhow::bail!("Length {} is below the policy's min_length {}", length, min);
+        }
+        if let Some(max) = self.max_length.filter(|max| length > *max) {
+            anyhow::bail!("Length {} is above the policy's max_length {}", length, max);
+        }
+        Ok(length)
+    }
+
+    /// Estimate the entropy in bits of passwords generated under the policy.
+    ///
+    /// Rejected candidates shrink the space of possible passwords, so the share of random
+    /// candidates the policy accepts is subtracted from the bits of the character pool.
+    pub fn entropy(&self, length: usize, opts: &CharsetOpts) -> anyhow::Result<f64> {
+        let opts = self.charset(opts);
+        self.ensure_satisfiable(length, &opts)?;
+        let mut accepted = 0;
+        for _ in 0..ENTROPY_SAMPLES {
+            if self.check(&process_genpass(length, &opts)?).is_ok() {
+                accepted += 1;
+            }
+        }
+        // 一个都没通过时按最乐观的比例估算，生成时会给出具体错误
+        let share = accepted.max(1) as f64 / ENTROPY_SAMPLES as f64;
+        Ok(entropy_bits(charset_size(&opts)?, length) + share.log2())
+    }
+
+    /// Apply the policy's symbol set unless the command line chose one.
+    pub fn charset(&self, opts: &CharsetOpts) -> CharsetOpts {
+        let mut opts = opts.clone();
+        if opts.symbols.is_none() {
+            opts.symbols.clone_from(&self.symbols);
+        }
+        opts
+    }
+
+    /// Return the first rule `password` breaks.
+    pub fn check(&self, password: &str) -> Result<(), String> {
+        let length = password.chars().count();
+        if let Some(min) = self.min_length.filter(|min| length < *min) {
+            return Err(format!("shorter than {} characters", min));
+        }
+        if let Some(max) = self.max_length.filter(|max| length > *max) {
+            return Err(format!("longer than {} characters", max));
+        }
+        if self.start_with_letter && !password.starts_with(char::is_alphabetic) {
+            return Err("does not start with a letter".to_string());
+        }
+        let lower = password.to_lowercase();
+        if let Some(word) = self
+            .forbidden
+            .iter()
+            .find(|word| lower.contains(&word.to_lowercase()))
+        {
+            return Err(format!("contains {:?}", word));
+        }
+        if let Some(max) = self.max_repeat {
+            let mut run = 0;
+            let mut last = None;
+            for c in password.chars() {
+                run = if last == Some(c) { run + 1 } else { 1 };
+                last = Some(c);
+                if run > max {
+                    return Err(format!("repeats {:?} more than {} times in a row", c, max));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 先排除明显无法满足的策略，给出具体原因而不是反复重试
+    fn ensure_satisfiable(&self, length: usize, opts: &CharsetOpts) -> anyhow::Result<()> {
+        if let (Some(min), Some(max)) = (self.min_length, self.max_length) {
+            if min > max {
+                anyhow::bail!("Policy min_length {} is above max_length {}", min, max);
+            }
+        }
+        if self.max_repeat == Some(0) {
+            anyhow::bail!("Policy max_repeat must be at least 1");
+        }
+        if let Some(word) = self.forbidden.iter().find(|word| word.is_empty()) {
+            anyhow::bail!("Policy forbids an empty substring {:?}", word);
+        }
+        let classes = char_classes(opts)?;
+        for required in &self.require {
+            if !classes.iter().any(|(class, _)| class == required) {
+                anyhow::bail!(
+                    "Policy requires {} characters, but they are disabled",
+                    required
+                );
+            }
+        }
+        if self.start_with_letter
+            && !classes
+                .iter()
+                .any(|(class, _)| matches!(class, CharClass::Upper | CharClass::Lower))
+        {
+            anyhow::bail!("Policy requires a leading letter, but letters are disabled");
+        }
+        if length < classes.len() {
+            anyhow::bail!(
+                "Policy allows at most {} characters, too few for {} character types",
+                length,
+                classes.len()
+            );
+        }
+        let pool: usize = classes.iter().map(|(_, set)| set.len()).sum();
+        if pool == 1 && self.max_repeat.is_some_and(|max| max < length) {
+            anyhow::bail!("Policy max_repeat can't be met with a single allowed character");
+        }
+        Ok(())
+    }
+}
+
+/// Generate passwords until one satisfies `policy`.
+pub fn process_genpass_with_policy(
+    length: usize,
+    opts: &CharsetOpts,
+    policy: &PasswordPolicy,
+) -> anyhow::Result<String> {
+    let length = policy.length(Some(length))?;
+    let opts = policy.charset(opts);
+    policy.ensure_satisfiable(length, &opts)?;
+
+    let mut reason = String::new();
+    for _ in 0..MAX_ATTEMPTS {
+        let password = process_genpass(length, &opts)?;
+        match policy.check(&password) {
+            Ok(()) => return Ok(password),
+            Err(e) => reason = e,
+        }
+    }
+    anyhow::bail!(
+        "No password satisfied the policy after {} attempts, the last one {}",
+        MAX_ATTEMPTS,
+        reason
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_genpass_with_policy() -> anyhow::Result<()> {
+        let policy = load_policy("fixtures/oracle_policy.toml")?;
+        assert_eq!(policy.length(None)?, 16);
+        assert!(policy.length(Some(8)).is_err());
+        assert!(process_genpass_with_policy(31, &CharsetOpts::default(), &policy).is_err());
+        for _ in 0..20 {
+            let pwd = process_genpass_with_policy(12, &CharsetOpts::default(), &policy)?;
+            assert_eq!(pwd.len(), 12);
+            assert!(pwd.starts_with(|c: char| c.is_ascii_alphabetic()));
+            assert!(pwd.contains(|c: char| "#_$".contains(c)));
+            assert!(!pwd.contains(|c: char| "!@%^&*".contains(c)));
+            assert_eq!(policy.check(&pwd), Ok(()));
+        }
+        assert!(policy.check("Abc#1aaa2bcdefg").is_err());
+        assert!(policy.check("Oracle#123xyz").is_err());
+        assert!(policy.check("1Abc#23456789").is_err());
+
+        // 策略拒绝的候选越多，熵越低
+        let entropy = policy.entropy(12, &CharsetOpts::default())?;
+        let pool = entropy_bits(charset_size(&policy.charset(&CharsetOpts::default()))?, 12);
+        assert!(entropy < pool - 0.3, "{} vs {}", entropy, pool);
+        Ok(())
+    }
+
+    #[test]
+    fn test_unsatisfiable_policy() {
+        let opts = CharsetOpts {
+            no_symbols: true,
+            ..Default::default()
+        };
+        let policy = PasswordPolicy {
+            require: vec![CharClass::Symbol],
+            ..Default::default()
+        };
+        let err = process_genpass_with_policy(16, &opts, &policy).unwrap_err();
+        assert!(err.to_string().contains("requires symbol"));
+
+        let policy = PasswordPolicy {
+            min_length: Some(20),
+            max_length: Some(10),
+            ..Default::default()
+        };
+        assert!(process_genpass_with_policy(16, &CharsetOpts::default(), &policy).is_err());
+
+        let policy = PasswordPolicy {
+            forbidden: vec!["a".into(), "b".into()],
+            ..Default::default()
+        };
+        let opts = CharsetOpts {
+            symbols: Some("ab".into()),
+            exclude: Some("ab".into()),
+            ..Default::default()
+        };
+        // 符号全部被排除
+        assert!(process_genpass_with_policy(16, &opts, &policy).is_err());
+    }
+}
@@ -0,0 +1,1073 @@
+use crate::{
+    cli::OutputFormat, detect_content_format, detect_path_format, get_data_reader, read_document,
+};
+use anyhow::{anyhow, bail, Context};
+use regex::Regex;
+use serde_json::{Map, Number, Value};
+use std::{cmp::Ordering, io::Read, iter::Peekable, str::Chars};
+
+/// A jq style filter such as `.players[] | select(.age > 30) | .name`.
+///
+/// Supported are paths (`.a.b`, `.[0]`, `.[1:3]`, `.[]`, `?`), pipes and commas, literals, array
+/// and object construction, arithmetic, comparisons, `and`/`or`/`//`, `if ... then ... else ...
+/// end` and a set of common builtins such as `select`, `map`, `length`, `keys` and `sort_by`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum JqExpr {
+    Identity,
+    Literal(Value),
+    Field(Box<JqExpr>, String),
+    Index(Box<JqExpr>, Box<JqExpr>),
+    Slice(Box<JqExpr>, Option<Box<JqExpr>>, Option<Box<JqExpr>>),
+    Iterate(Box<JqExpr>),
+    /// `expr?`，忽略错误
+    Try(Box<JqExpr>),
+    Array(Option<Box<JqExpr>>),
+    Object(Vec<(JqExpr, Option<JqExpr>)>),
+    Pipe(Box<JqExpr>, Box<JqExpr>),
+    Comma(Box<JqExpr>, Box<JqExpr>),
+    Binary(Box<JqExpr>, BinOp, Box<JqExpr>),
+    And(Box<JqExpr>, Box<JqExpr>),
+    Or(Box<JqExpr>, Box<JqExpr>),
+    Alternative(Box<JqExpr>, Box<JqExpr>),
+    Neg(Box<JqExpr>),
+    If(Box<JqExpr>, Box<JqExpr>, Box<JqExpr>),
+    Call(String, Vec<JqExpr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Dot,
+    Field(String),
+    Ident(String),
+    Str(String),
+    Num(f64),
+    Op(BinOp),
+    Alt,
+    Pipe,
+    Comma,
+    Colon,
+    Semicolon,
+    Question,
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    LBrace,
+    RBrace,
+}
+
+/// Read a document in any format `rcli convert` understands and run `filter` over it.
+pub fn process_query(
+    input: &str,
+    filter: &str,
+    from: Option<OutputFormat>,
+) -> anyhow::Result<Vec<Value>> {
+    let filter = JqExpr::parse(filter)?;
+    let mut content = Vec::new();
+    get_data_reader(input)?.read_to_end(&mut content)?;
+    let from = match from.or_else(|| detect_path_format(input)) {
+        Some(from) => from,
+        None => detect_content_format(&content)?,
+    };
+    let document =
+        read_document(content, from).with_context(|| format!("{} is not valid {}", input, from))?;
+    filter.eval(&document)
+}
+
+impl JqExpr {
+    pub fn parse(input: &str) -> anyhow::Result<Self> {
+        let tokens = tokenize(input)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.parse_pipe()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            bail!("Unexpected {:?} in query: {}", token, input);
+        }
+        Ok(expr)
+    }
+
+    /// Run the filter, returning every value it produces.
+    pub fn eval(&self, input: &Value) -> anyhow::Result<Vec<Value>> {
+        Ok(match self {
+            JqExpr::Identity => vec![input.clone()],
+            JqExpr::Literal(v) => vec![v.clone()],
+            JqExpr::Field(target, name) => {
+                let mut out = Vec::new();
+                for value in target.eval(input)? {
+                    out.push(index(&value, &Value::String(name.clone()))?);
+                }
+                out
+            }
+            JqExpr::Index(target, idx) => {
+                let mut out = Vec::new();
+                for value in target.eval(input)? {
+                    for i in idx.eval(input)? {
+                        out.push(index(&value, &i)?);
+                    }
+                }
+                out
+            }
+            JqExpr::Slice(target, from, to) => {
+                let bound = |e: &Option<Box<JqExpr>>| -> anyhow::Result<Option<f64>> {
+                    match e {
+                        Some(e) => Ok(e.eval(input)?.first().and_then(Value::as_f64)),
+                        None => Ok(None),
+                    }
+                };
+                let (from, to) = (bound(from)?, bound(to)?);
+                let mut out = Vec::new();
+                for value in target.eval(input)? {
+                    out.push(slice(&value, from, to)?);
+                }
+                out
+            }
+            JqExpr::Iterate(target) => {
+                let mut out = Vec::new();
+                for value in target.eval(input)? {
+                    match value {
+                        Value::Array(items) => out.extend(items),
+                        Value::Object(map) => out.extend(map.into_iter().map(|(_, v)| v)),
+                        other => bail!("Cannot iterate over {}", type_name(&other)),
+                    }
+                }
+                out
+            }
+            JqExpr::Try(expr) => expr.eval(input).unwrap_or_default(),
+            JqExpr::Array(None) => vec![Value::Array(Vec::new())],
+            JqExpr::Array(Some(expr)) => vec![Value::Array(expr.eval(input)?)],
+            JqExpr::Object(entries) => {
+                // 每个键或值产生多个结果时，取所有组合
+                let mut objects = vec![Map::new()];
+                for (key, value) in entries {
+                    let keys = key.eval(input)?;
+                    let values = match value {
+                        Some(value) => value.eval(input)?,
+                        None => {
+                            JqExpr::Field(Box::new(JqExpr::Identity), key_name(key)?).eval(input)?
+                        }
+                    };
+                    let mut next = Vec::new();
+                    for object in &objects {
+                        for k in &keys {
+                            let Value::String(k) = k else {
+                                bail!("Object keys must be strings, got {}", type_name(k));
+                            };
+                            for v in &values {
+                                let mut object = object.clone();
+                                object.insert(k.clone(), v.clone());
+                                next.push(object);
+                            }
+                        }
+                    }
+                    objects = next;
+                }
+                objects.into_iter().map(Value::Object).collect()
+            }
+            JqExpr::Pipe(left, right) => {
+                let mut out = Vec::new();
+                for value in left.eval(input)? {
+                    out.extend(right.eval(&value)?);
+                }
+                out
+            }
+            JqExpr::Comma(left, right) => {
+                let mut out = left.eval(input)?;
+                out.extend(right.eval(input)?);
+                out
+            }
+            JqExpr::Binary(left, op, right) => {
+                let mut out = Vec::new();
+                for r in right.eval(input)? {
+                    for l in left.eval(input)? {
+                        out.push(binary(&l, *op, &r)?);
+                    }
+                }
+                out
+            }
+            JqExpr::And(left, right) | JqExpr::Or(left, right) => {
+                let is_and = matches!(self, JqExpr::And(..));
+                let mut out = Vec::new();
+                for l in left.eval(input)? {
+                    if truthy(&l) != is_and {
+                        out.push(Value::Bool(!is_and));
+                        continue;
+                    }
+                    for r in right.eval(input)? {
+                        out.push(Value::Bool(truthy(&r)));
+                    }
+                }
+                out
+            }
+            JqExpr::Alternative(left, right) => {
+                let values: Vec<Value> = left
+                    .eval(input)
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter(truthy)
+                    .collect();
+                if values.is_empty() {
+                    right.eval(input)?
+                } else {
+                    values
+                }
+            }
+            JqExpr::Neg(expr) => {
+                let mut out = Vec::new();
+                for value in expr.eval(input)? {
+                    match value.as_f64() {
+                        Some(n) => out.push(number(-n)),
+                        None => bail!("Cannot negate {}", type_name(&value)),
+                    }
+                }
+                out
+            }
+            JqExpr::If(cond, then, otherwise) => {
+                let mut out = Vec::new();
+                for c in cond.eval(input)? {
+                    if truthy(&c) {
+                        out.extend(then.eval(input)?);
+                    } else {
+                        out.extend(otherwise.eval(input)?);
+                    }
+                }
+                out
+            }
+            JqExpr::Call(name, args) => call(name, args, input)?,
+        })
+    }
+}
+
+fn key_name(key: &JqExpr) -> anyhow::Result<String> {
+    match key {
+        JqExpr::Literal(Value::String(s)) => Ok(s.clone()),
+        _ => bail!("Object entries with computed keys need a value"),
+    }
+}
+
+fn call(name: &str, args: &[JqExpr], input: &Value) -> anyhow::Result<Vec<Value>> {
+    let arg = |i: usize| -> anyhow::Result<Value> {
+        args[i]
+            .eval(input)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow!("{} argument produced no value", name))
+    };
+    let string = |i: usize| -> anyhow::Result<String> {
+        match arg(i)? {
+            Value::String(s) => Ok(s),
+            other => bail!("{} expects a string, got {}", name, type_name(&other)),
+        }
+    };
+    let items = || -> anyhow::Result<Vec<Value>> {
+        match input {
+            Value::Array(items) => Ok(items.clone()),
+            other => bail!("{} expects an array, got {}", name, type_name(other)),
+        }
+    };
+    let text = || -> anyhow::Result<&str> {
+        input
+            .as_str()
+            .ok_or_else(|| anyhow!("{} expects a string, got {}", name, type_name(input)))
+    };
+
+    let value = match (name, args.len()) {
+        ("empty", 0) => return Ok(Vec::new()),
+        ("not", 0) => Value::Bool(!truthy(input)),
+        ("select", 1) => {
+            let keep = args[0].eval(input)?.iter().any(truthy);
+            return Ok(if keep {
+                vec![input.clone()]
+            } else {
+                Vec::new()
+            });
+        }
+        ("map", 1) => {
+            let mut out = Vec::new();
+            for item in items()? {
+                out.extend(args[0].eval(&item)?);
+            }
+            Value::Array(out)
+        }
+        ("length", 0) => match input {
+            Value::Null => Value::from(0),
+            Value::Number(n) => number(n.as_f64().unwrap_or_default().abs()),
+            Value::String(s) => Value::from(s.chars().count()),
+            Value::Array(items) => Value::from(items.len()),
+            Value::Object(map) => Value::from(map.len()),
+            Value::Bool(_) => bail!("boolean has no length"),
+        },
+        ("keys", 0) => match input {
+            Value::Object(map) => {
+                let mut keys: Vec<&String> = map.keys().collect();
+                keys.sort();
+                Value::Array(keys.into_iter().map(|k| Value::from(k.as_str())).collect())
+            }
+            Value::Array(items) => Value::Array((0..items.len()).map(Value::from).collect()),
+            other => bail!("{} has no keys", type_name(other)),
+        },
+        ("has", 1) => match (input, arg(0)?) {
+            (Value::Object(map), Value::String(k)) => Value::Bool(map.contains_key(&k)),
+            (Value::Array(items), Value::Number(n)) => Value::Bool(
+                n.as_f64()
+                    .is_some_and(|i| i >= 0.0 && (i as usize) < items.len()),
+            ),
+            (value, key) => bail!(
+                "Cannot check whether {} has a {} key",
+                type_name(value),
+                type_name(&key)
+            ),
+        },
+        ("type", 0) => Value::from(type_name(input)),
+        ("add", 0) => {
+            let mut total = Value::Null;
+            for item in items()? {
+                total = binary(&total, BinOp::Add, &item)?;
+            }
+            total
+        }
+        ("first", 0) => items()?.into_iter().next().unwrap_or(Value::Null),
+        ("last", 0) => items()?.pop().unwrap_or(Value::Null),
+        ("reverse", 0) => Value::Array(items()?.into_iter().rev().collect()),
+        ("sort", 0) => {
+            let mut items = items()?;
+            items.sort_by(compare);
+            Value::Array(items)
+        }
+        ("sort_by", 1) => {
+            let mut keyed = items()?
+                .into_iter()
+                .map(|item| Ok((args[0].eval(&item)?, item)))
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            keyed.sort_by(|a, b| compare(&Value::Array(a.0.clone()), &Value::Array(b.0.clone())));
+            Value::Array(keyed.into_iter().map(|(_, item)| item).collect())
+        }
+        ("unique", 0) => {
+            let mut items = items()?;
+            items.sort_by(compare);
+            items.dedup();
+            Value::Array(items)
+        }
+        ("min", 0) => items()?.into_iter().min_by(compare).unwrap_or(Value::Null),
+        ("max", 0) => items()?.into_iter().max_by(compare).unwrap_or(Value::Null),
+        ("any", 0) => Value::Bool(items()?.iter().any(truthy)),
+        ("all", 0) => Value::Bool(items()?.iter().all(truthy)),
+        ("tostring", 0) => match input {
+            Value::String(_) => input.clone(),
+            _ => Value::String(input.to_string()),
+        },
+        ("tonumber", 0) => match input {
+            Value::Number(_) => input.clone(),
+            Value::String(s) => number(
+                s.trim()
+                    .parse()
+                    .with_context(|| format!("Cannot parse {:?} as a number", s))?,
+            ),
+            other => bail!("Cannot convert {} to a number", type_name(other)),
+        },
+        ("ascii_downcase", 0) => Value::from(text()?.to_ascii_lowercase()),
+        ("ascii_upcase", 0) => Value::from(text()?.to_ascii_uppercase()),
+        ("startswith", 1) => Value::Bool(text()?.starts_with(&string(0)?)),
+        ("endswith", 1) => Value::Bool(text()?.ends_with(&string(0)?)),
+        ("test", 1) => Value::Bool(Regex::new(&string(0)?)?.is_match(text()?)),
+        ("split", 1) => Value::Array(
+            text()?
+                .split(string(0)?.as_str())
+                .map(Value::from)
+                .collect(),
+        ),
+        ("join", 1) => {
+            let separator = string(0)?;
+            let parts = items()?
+                .iter()
+                .map(|item| match item {
+                    Value::Null => Ok(String::new()),
+                    Value::String(s) => Ok(s.clone()),
+                    Value::Number(_) | Value::Bool(_) => Ok(item.to_string()),
+                    other => bail!("Cannot join {}", type_name(other)),
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            Value::from(parts.join(&separator))
+        }
+        ("to_entries", 0) => match input {
+            Value::Object(map) => Value::Array(
+                map.iter()
+                    .map(|(k, v)| serde_json::json!({"key": k, "value": v}))
+                    .collect(),
+            ),
+            other => bail!("{} has no entries", type_name(other)),
+        },
+        ("from_entries", 0) => {
+            let mut map = Map::new();
+            for entry in items()? {
+                let key = match (entry.get("key"), entry.get("name")) {
+                    (Some(Value::String(k)), _) | (None, Some(Value::String(k))) => k.clone(),
+                    (Some(k), _) if !k.is_null() => k.to_string(),
+                    _ => bail!("from_entries needs a key in every entry"),
+                };
+                map.insert(key, entry.get("value").cloned().unwrap_or(Value::Null));
+            }
+            Value::Object(map)
+        }
+        _ => bail!("Unknown function {}/{}", name, args.len()),
+    };
+    Ok(vec![value])
+}
+
+fn index(value: &Value, key: &Value) -> anyhow::Result<Value> {
+    Ok(match (value, key) {
+        (Value::Null, _) => Value::Null,
+        (Value::Object(map), Value::String(k)) => map.get(k).cloned().unwrap_or(Value::Null),
+        (Value::Array(items), Value::Number(n)) => {
+            let i = n.as_f64().unwrap_or_default() as i64;
+            let i = if i < 0 { items.len() as i64 + i } else { i };
+            usize::try_from(i)
+                .ok()
+                .and_then(|i| items.get(i))
+                .cloned()
+                .unwrap_or(Value::Null)
+        }
+        (value, key) => bail!(
+            "Cannot index {} with {}",
+            type_name(value),
+            match key {
+                Value::String(s) => format!("{:?}", s),
+                _ => type_name(key).to_string(),
+            }
+        ),
+    })
+}
+
+fn slice(value: &Value, from: Option<f64>, to: Option<f64>) -> anyhow::Result<Value> {
+    let range = |len: usize| {
+        let clamp = |n: f64| {
+            let n = if n < 0.0 { len as f64 + n } else { n };
+            n.clamp(0.0, len as f64) as usize
+        };
+        let start = from.map_or(0, clamp);
+        let end = to.map_or(len, clamp).max(start);
+        start..end
+    };
+    Ok(match value {
+        Value::Null => Value::Null,
+        Value::Array(items) => Value::Array(items[range(items.len())].to_vec()),
+        Value::String(s) => {
+            let chars: Vec<char> = s.chars().collect();
+            Value::String(chars[range(chars.len())].iter().collect())
+        }
+        other => bail!("Cannot slice {}", type_name(other)),
+    })
+}
+
+fn binary(l: &Value, op: BinOp, r: &Value) -> anyhow::Result<Value> {
+    let ordering = || compare(l, r);
+    Ok(match op {
+        BinOp::Eq => Value::Bool(ordering().is_eq()),
+        BinOp::Ne => Value::Bool(ordering().is_ne()),
+        BinOp::Lt => Value::Bool(ordering().is_lt()),
+        BinOp::Le => Value::Bool(ordering().is_le()),
+        BinOp::Gt => Value::Bool(ordering().is_gt()),
+        BinOp::Ge => Value::Bool(ordering().is_ge()),
+        BinOp::Add => match (l, r) {
+            (Value::Null, v) | (v, Value::Null) => v.clone(),
+            (Value::Number(a), Value::Number(b)) => {
+                number(a.as_f64().unwrap_or_default() + b.as_f64().unwrap_or_default())
+            }
+            (Value::String(a), Value::String(b)) => Value::String(format!("{}{}", a, b)),
+            (Value::Array(a), Value::Array(b)) => Value::Array([a.clone(), b.clone()].concat()),
+            (Value::Object(a), Value::Object(b)) => {
+                let mut merged = a.clone();
+                merged.extend(b.clone());
+                Value::Object(merged)
+            }
+            _ => bail!("Cannot add {} and {}", type_name(l), type_name(r)),
+        },
+        BinOp::Sub => match (l, r) {
+            (Value::Number(a), Value::Number(b)) => {
+                number(a.as_f64().unwrap_or_default() - b.as_f64().unwrap_or_default())
+            }
+            (Value::Array(a), Value::Array(b)) => {
+                Value::Array(a.iter().filter(|v| !b.contains(v)).cloned().collect())
+            }
+            _ => bail!("Cannot subtract {} from {}", type_name(r), type_name(l)),
+        },
+        BinOp::Mul | BinOp::Div | BinOp::Mod => {
+            let (Some(a), Some(b)) = (l.as_f64(), r.as_f64()) else {
+                if let (BinOp::Div, Value::String(a), Value::String(b)) = (op, l, r) {
+                    return Ok(Value::Array(a.split(b.as_str()).map(Value::from).collect()));
+                }
+                bail!(
+                    "Cannot apply {:?} to {} and {}",
+                    op,
+                    type_name(l),
+                    type_name(r)
+                );
+            };
+            match op {
+                BinOp::Mul => number(a * b),
+                BinOp::Div if b == 0.0 => bail!("Division by zero"),
+                BinOp::Div => number(a / b),
+                // 与 jq 一样先截断为整数，所以 `% 0.5` 的除数也是零
+                _ => match b as i64 {
+                    0 => bail!("Division by zero"),
+                    // 只有 i64::MIN % -1 会溢出，其结果为零
+                    d => number((a as i64).checked_rem(d).unwrap_or(0) as f64),
+                },
+            }
+        }
+    })
+}
+
+/// Order values like jq: null < false < true < numbers < strings < arrays < objects.
+fn compare(a: &Value, b: &Value) -> Ordering {
+    fn rank(v: &Value) -> u8 {
+        match v {
+            Value::Null => 0,
+            Value::Bool(false) => 1,
+            Value::Bool(true) => 2,
+            Value::Number(_) => 3,
+            Value::String(_) => 4,
+            Value::Array(_) => 5,
+            Value::Object(_) => 6,
+        }
+    }
+    match (a, b) {
+        (Value::Number(x), Value::Number(y)) => x
+            .as_f64()
+            .unwrap_or_default()
+            .total_cmp(&y.as_f64().unwrap_or_default()),
+        (Value::String(x), Value::String(y)) => x.cmp(y),
+        (Value::Array(x), Value::Array(y)) => x
+            .iter()
+            .zip(y)
+            .map(|(x, y)| compare(x, y))
+            .find(|o| o.is_ne())
+            .unwrap_or_else(|| x.len().cmp(&y.len())),
+        (Value::Object(x), Value::Object(y)) => {
+            let keys = |m: &Map<String, Value>| {
+                let mut keys: Vec<String> = m.keys().cloned().collect();
+                keys.sort();
+                keys
+            };
+            keys(x).cmp(&keys(y)).then_with(|| {
+                keys(x)
+                    .iter()
+                    .map(|k| compare(&x[k], &y[k]))
+                    .find(|o| o.is_ne())
+                    .unwrap_or(Ordering::Equal)
+            })
+        }
+        _ => rank(a).cmp(&rank(b)),
+    }
+}
+
+/// 整数结果保持为整数，避免输出 2.0
+fn number(n: f64) -> Value {
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        Value::from(n as i64)
+    } else {
+        Number::from_f64(n).map_or(Value::Null, Value::Number)
+    }
+}
+
+fn truthy(value: &Value) -> bool {
+    !matches!(value, Value::Null | Value::Bool(false))
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &Token) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        self.eat(&Token::Ident(keyword.to_string()))
+    }
+
+    fn expect(&mut self, token: Token) -> anyhow::Result<()> {
+        match self.next() {
+            Some(t) if t == token => Ok(()),
+            Some(t) => bail!("Expected {:?} in query, found {:?}", token, t),
+            None => bail!("Expected {:?} at the end of query", token),
+        }
+    }
+
+    fn parse_pipe(&mut self) -> anyhow::Result<JqExpr> {
+        let left = self.parse_comma()?;
+        if self.eat(&Token::Pipe) {
+            return Ok(JqExpr::Pipe(Box::new(left), Box::new(self.parse_pipe()?)));
+        }
+        Ok(left)
+    }
+
+    fn parse_comma(&mut self) -> anyhow::Result<JqExpr> {
+        let mut left = self.parse_alternative()?;
+        while self.eat(&Token::Comma) {
+            left = JqExpr::Comma(Box::new(left), Box::new(self.parse_alternative()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_alternative(&mut self) -> anyhow::Result<JqExpr> {
+        let left = self.parse_or()?;
+        if self.eat(&Token::Alt) {
+            // `//` 是右结合的
+            return Ok(JqExpr::Alternative(
+                Box::new(left),
+                Box::new(self.parse_alternative()?),
+            ));
+        }
+        Ok(left)
+    }
+
+    fn parse_or(&mut self) -> anyhow::Result<JqExpr> {
+        let mut left = self.parse_and()?;
+        while self.eat_keyword("or") {
+            left = JqExpr::Or(Box::new(left), Box::new(self.parse_and()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_and(&mut self) -> anyhow::Result<JqExpr> {
+        let mut left = self.parse_compare()?;
+        while self.eat_keyword("and") {
+            left = JqExpr::And(Box::new(left), Box::new(self.parse_compare()?));
+        }
+        Ok(left)
+    }
+
+    fn parse_compare(&mut self) -> anyhow::Result<JqExpr> {
+        let left = self.parse_additive()?;
+        if let Some(Token::Op(
+            op @ (BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge),
+        )) = self.peek().cloned()
+        {
+            self.pos += 1;
+            let right = self.parse_additive()?;
+            return Ok(JqExpr::Binary(Box::new(left), op, Box::new(right)));
+        }
+        Ok(left)
+    }
+
+    fn parse_additive(&mut self) -> anyhow::Result<JqExpr> {
+        let mut left = self.parse_multiplicative()?;
+        while let Some(Token::Op(op @ (BinOp::Add | BinOp::Sub))) = self.peek().cloned() {
+            self.pos += 1;
+            let right = self.parse_multiplicative()?;
+            left = JqExpr::Binary(Box::new(left), op, Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_multiplicative(&mut self) -> anyhow::Result<JqExpr> {
+        let mut left = self.parse_postfix()?;
+        while let Some(Token::Op(op @ (BinOp::Mul | BinOp::Div | BinOp::Mod))) =
+            self.peek().cloned()
+        {
+            self.pos += 1;
+            let right = self.parse_postfix()?;
+            left = JqExpr::Binary(Box::new(left), op, Box::new(right));
+        }
+        Ok(left)
+    }
+
+    fn parse_postfix(&mut self) -> anyhow::Result<JqExpr> {
+        let mut expr = self.parse_primary()?;
+        loop {
+            expr = match self.peek() {
+                Some(Token::Field(name)) => {
+                    let name = name.clone();
+                    self.pos += 1;
+                    JqExpr::Field(Box::new(expr), name)
+                }
+                Some(Token::LBracket) => {
+                    self.pos += 1;
+                    self.parse_brackets(expr)?
+                }
+                // `.a.[0]` 中间的点可以省略
+                Some(Token::Dot) if self.tokens.get(self.pos + 1) == Some(&Token::LBracket) => {
+                    self.pos += 2;
+                    self.parse_brackets(expr)?
+                }
+                Some(Token::Question) => {
+                    self.pos += 1;
+                    JqExpr::Try(Box::new(expr))
+                }
+                _ => return Ok(expr),
+            };
+        }
+    }
+
+    /// 解析 `[` 之后的部分：`[]`、`[i]`、`[a:b]`
+    fn parse_brackets(&mut self, target: JqExpr) -> anyhow::Result<JqExpr> {
+        let target = Box::new(target);
+        if self.eat(&Token::RBracket) {
+            return Ok(JqExpr::Iterate(target));
+        }
+        let from = if self.peek() == Some(&Token::Colon) {
+            None
+        } else {
+            Some(Box::new(self.parse_pipe()?))
+        };
+        if self.eat(&Token::Colon) {
+            let to = if self.peek() == Some(&Token::RBracket) {
+                None
+            } else {
+                Some(Box::new(self.parse_pipe()?))
+            };
+            self.expect(Token::RBracket)?;
+            return Ok(JqExpr::Slice(target, from, to));
+        }
+        self.expect(Token::RBracket)?;
+        match from {
+            Some(index) => Ok(JqExpr::Index(target, index)),
+            None => bail!("Empty index in query"),
+        }
+    }
+
+    fn parse_primary(&mut self) -> anyhow::Result<JqExpr> {
+        Ok(match self.next() {
+            Some(Token::Dot) => JqExpr::Identity,
+            Some(Token::Field(name)) => JqExpr::Field(Box::new(JqExpr::Identity), name),
+            Some(Token::Num(n)) => JqExpr::Literal(number(n)),
+            Some(Token::Str(s)) => JqExpr::Literal(Value::String(s)),
+            Some(Token::Op(BinOp::Sub)) => JqExpr::Neg(Box::new(self.parse_postfix()?)),
+            Some(Token::LParen) => {
+                let expr = self.parse_pipe()?;
+                self.expect(Token::RParen)?;
+                expr
+            }
+            Some(Token::LBracket) => {
+                if self.eat(&Token::RBracket) {
+                    JqExpr::Array(None)
+                } else {
+                    let expr = self.parse_pipe()?;
+                    self.expect(Token::RBracket)?;
+                    JqExpr::Array(Some(Box::new(expr)))
+                }
+            }
+            Some(Token::LBrace) => self.parse_object()?,
+            Some(Token::Ident(name)) => match name.as_str() {
+                "true" => JqExpr::Literal(Value::Bool(true)),
+                "false" => JqExpr::Literal(Value::Bool(false)),
+                "null" => JqExpr::Literal(Value::Null),
+                "if" => self.parse_if()?,
+                _ => {
+                    let mut args = Vec::new();
+                    if self.eat(&Token::LParen) {
+                        loop {
+                            args.push(self.parse_pipe()?);
+                            if !self.eat(&Token::Semicolon) {
+                                break;
+                            }
+                        }
+                        self.expect(Token::RParen)?;
+                    }
+                    JqExpr::Call(name, args)
+                }
+            },
+            Some(token) => bail!("Unexpected {:?} in query", token),
+            None => bail!("Unexpected end of query"),
+        })
+    }
+
+    fn parse_if(&mut self) -> anyhow::Result<JqExpr> {
+        let cond = self.parse_pipe()?;
+        if !self.eat_keyword("then") {
+            bail!("Expected then after if in query");
+        }
+        let then = self.parse_pipe()?;
+        let otherwise = if self.eat_keyword("elif") {
+            return Ok(JqExpr::If(
+                Box::new(cond),
+                Box::new(then),
+                Box::new(self.parse_if()?),
+            ));
+        } else if self.eat_keyword("else") {
+            self.parse_pipe()?
+        } else {
+            JqExpr::Identity
+        };
+        if !self.eat_keyword("end") {
+            bail!("Expected end to close if in query");
+        }
+        Ok(JqExpr::If(
+            Box::new(cond),
+            Box::new(then),
+            Box::new(otherwise),
+        ))
+    }
+
+    fn parse_object(&mut self) -> anyhow::Result<JqExpr> {
+        let mut entries = Vec::new();
+        if self.eat(&Token::RBrace) {
+            return Ok(JqExpr::Object(entries));
+        }
+        loop {
+            let key = match self.next() {
+                Some(Token::Ident(name)) | Some(Token::Str(name)) => {
+                    JqExpr::Literal(Value::String(name))
+                }
+                Some(Token::LParen) => {
+                    let key = self.parse_pipe()?;
+                    self.expect(Token::RParen)?;
+                    key
+                }
+                Some(token) => bail!("Unexpected {:?} as an object key in query", token),
+                None => bail!("Unexpected end of query in object"),
+            };
+            let value = if self.eat(&Token::Colon) {
+                Some(self.parse_alternative()?)
+            } else {
+                None
+            };
+            entries.push((key, value));
+            if self.eat(&Token::RBrace) {
+                return Ok(JqExpr::Object(entries));
+            }
+            self.expect(Token::Comma)?;
+        }
+    }
+}
+
+fn tokenize(input: &str) -> anyhow::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        let token = match c {
+            c if c.is_whitespace() => {
+                chars.next();
+                continue;
+            }
+            '.' => {
+                chars.next();
+                match chars.peek() {
+                    Some(c) if c.is_alphabetic() || *c == '_' => {
+                        Token::Field(read_ident(&mut chars))
+                    }
+                    Some('"') => Token::Field(read_string(&mut chars)?),
+                    _ => Token::Dot,
+                }
+            }
+            '"' => Token::Str(read_string(&mut chars)?),
+            c if c.is_ascii_digit() => {
+                let mut number = String::new();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || *c == '.') {
+                    number.push(c);
+                }
+                if let Some(e) = chars.next_if(|c| *c == 'e' || *c == 'E') {
+                    number.push(e);
+                    if let Some(sign) = chars.next_if(|c| *c == '+' || *c == '-') {
+                        number.push(sign);
+                    }
+                    while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+                        number.push(c);
+                    }
+                }
+                Token::Num(
+                    number
+                        .parse()
+                        .map_err(|_| anyhow!("Invalid number in query: {}", number))?,
+                )
+            }
+            c if c.is_alphabetic() || c == '_' => Token::Ident(read_ident(&mut chars)),
+            _ => {
+                chars.next();
+                match c {
+                    '|' => Token::Pipe,
+                    ',' => Token::Comma,
+                    ':' => Token::Colon,
+                    ';' => Token::Semicolon,
+                    '?' => Token::Question,
+                    '(' => Token::LParen,
+                    ')' => Token::RParen,
+                    '[' => Token::LBracket,
+                    ']' => Token::RBracket,
+                    '{' => Token::LBrace,
+                    '}' => Token::RBrace,
+                    '+' => Token::Op(BinOp::Add),
+                    '-' => Token::Op(BinOp::Sub),
+                    '*' => Token::Op(BinOp::Mul),
+                    '%' => Token::Op(BinOp::Mod),
+                    '/' if chars.next_if_eq(&'/').is_some() => Token::Alt,
+                    '/' => Token::Op(BinOp::Div),
+                    '=' if chars.next_if_eq(&'=').is_some() => Token::Op(BinOp::Eq),
+                    '!' if chars.next_if_eq(&'=').is_some() => Token::Op(BinOp::Ne),
+                    '<' if chars.next_if_eq(&'=').is_some() => Token::Op(BinOp::Le),
+                    '<' => Token::Op(BinOp::Lt),
+                    '>' if chars.next_if_eq(&'=').is_some() => Token::Op(BinOp::Ge),
+                    '>' => Token::Op(BinOp::Gt),
+                    _ => bail!("Unexpected character {:?} in query", c),
+                }
+            }
+        };
+        tokens.push(token);
+    }
+    Ok(tokens)
+}
+
+fn read_ident(chars: &mut Peekable<Chars>) -> String {
+    let mut ident = String::new();
+    while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+        ident.push(c);
+    }
+    ident
+}
+
+fn read_string(chars: &mut Peekable<Chars>) -> anyhow::Result<String> {
+    chars.next();
+    let mut s = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(s),
+            Some('\\') => match chars.next() {
+                Some('n') => s.push('\n'),
+                Some('t') => s.push('\t'),
+                Some('r') => s.push('\r'),
+                Some('u') => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    let c = u32::from_str_radix(&hex, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .ok_or_else(|| anyhow!("Invalid \\u escape in query: {}", hex))?;
+                    s.push(c);
+                }
+                Some(c) => s.push(c),
+                None => bail!("Unterminated string in query"),
+            },
+            Some(c) => s.push(c),
+            None => bail!("Unterminated string in query"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn run(filter: &str, input: &Value) -> anyhow::Result<Vec<Value>> {
+        JqExpr::parse(filter)?.eval(input)
+    }
+
+    #[test]
+    fn test_jq_paths_and_filters() -> anyhow::Result<()> {
+        let team = json!({
+            "name": "Juventus",
+            "players": [
+                {"name": "Buffon", "age": 41, "tags": ["captain"]},
+                {"name": "Dybala", "age": 25, "tags": []},
+                {"name": "Chiellini", "age": 35}
+            ]
+        });
+        assert_eq!(
+            run(".players[] | select(.age > 30) | .name", &team)?,
+            vec![json!("Buffon"), json!("Chiellini")]
+        );
+        assert_eq!(run(".players[-1].name", &team)?, vec![json!("Chiellini")]);
+        assert_eq!(run(".players[1:].[0].age", &team)?, vec![json!(25)]);
+        assert_eq!(
+            run("[.players[].age] | add / length", &team)?,
+            vec![json!(33.666666666666664)]
+        );
+        assert_eq!(
+            run(".players | map({name, senior: (.age >= 35)}) | .[0]", &team)?,
+            vec![json!({"name": "Buffon", "senior": true})]
+        );
+        assert_eq!(
+            run(
+                ".players | sort_by(.age) | map(.name) | join(\", \")",
+                &team
+            )?,
+            vec![json!("Dybala, Chiellini, Buffon")]
+        );
+        assert_eq!(
+            run(".players[] | .tags[0] // \"none\"", &team)?,
+            vec![json!("captain"), json!("none"), json!("none")]
+        );
+        assert_eq!(
+            run(
+                ".players[0] | if .age > 40 then \"veteran\" elif .age > 30 then \"senior\" else \"young\" end",
+                &team
+            )?,
+            vec![json!("veteran")]
+        );
+        assert_eq!(
+            run(".name, (.players | length)", &team)?,
+            vec![json!("Juventus"), json!(3)]
+        );
+        assert_eq!(
+            run(".name | test(\"^Ju\") and (. | length) == 8", &team)?,
+            vec![json!(true)]
+        );
+        assert_eq!(run(".missing.deeper", &team)?, vec![Value::Null]);
+        Ok(())
+    }
+
+    #[test]
+    fn test_jq_errors() {
+        let team = json!({"name": "Juventus"});
+        assert!(run(".name[]", &team).is_err());
+        assert!(run(".name.first", &team).is_err());
+        assert_eq!(run(".name.first?", &team).ok(), Some(Vec::new()));
+        assert!(JqExpr::parse(".players[").is_err());
+        assert!(JqExpr::parse("select(.a > )").is_err());
+        assert!(run("nosuchfn", &team).is_err());
+        assert!(run(".a % 0.5", &json!({"a": 3})).is_err());
+        assert_eq!(run(". % -1", &json!(i64::MIN)).ok(), Some(vec![json!(0)]));
+    }
+
+    #[test]
+    fn test_process_query_yaml() -> anyhow::Result<()> {
+        let names = process_query(
+            "fixtures/team.yaml",
+            ".players[] | select(.age > 30) | .name",
+            None,
+        )?;
+        assert_eq!(names, vec![json!("Gianluigi Buffon")]);
+        Ok(())
+    }
+}
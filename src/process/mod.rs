@@ -1,13 +1,57 @@
 mod b64;
+mod convert;
 mod csv_covert;
+mod csv_dedupe;
+mod csv_diff;
+mod csv_filter;
+mod csv_join;
+mod csv_merge;
+mod csv_query;
+mod csv_sink;
+mod csv_sort;
+mod csv_split;
+mod csv_stats;
+mod csv_table;
+mod csv_validate;
+mod encoding;
+mod format;
 mod gen_pass;
+mod gen_policy;
 mod http_serve;
+mod jq;
+mod password;
+mod sketch;
 mod text;
+mod to_csv;
 
 pub use self::{
     b64::{process_decode, process_encode},
-    csv_covert::process_csv,
-    gen_pass::process_genpass,
+    convert::{
+        detect_content_format, detect_path_format, process_convert, read_document, write_document,
+    },
+    csv_covert::{csv_reader, process_csv, CsvRecords, RecordConverter, TypeGuess},
+    csv_dedupe::process_csv_dedupe,
+    csv_diff::{process_csv_diff, render_diff, CellChange, CsvDiff, RowChange},
+    csv_filter::Filter,
+    csv_join::process_csv_join,
+    csv_merge::process_csv_merge,
+    csv_query::{process_csv_query, table_name, QueryResult},
+    csv_sink::{compare_cells, RowSink},
+    csv_sort::process_csv_sort,
+    csv_split::{process_csv_split, SplitBy},
+    csv_stats::{process_csv_stats, render_stats, ColumnStats, ValueCount},
+    csv_table::{process_csv_show, render_table, terminal_width},
+    csv_validate::{load_schema, process_csv_validate, ColumnRule, CsvSchema, ValidationError},
+    encoding::decode_reader,
+    format::{read_records, record_writer, RecordWriter},
+    gen_pass::{
+        charset_size, entropy_bits, load_wordlist, process_genpass, process_genphrase,
+        GeneratedPassword, PasswordWriter, DEFAULT_LENGTH,
+    },
+    gen_policy::{load_policy, process_genpass_with_policy, PasswordPolicy},
     http_serve::process_http_serve,
+    jq::{process_query, BinOp, JqExpr},
+    password::{process_password_check, render_password_report, CrackTimeReport, PasswordReport},
     text::{process_text_generate, process_text_sign, process_text_verify},
+    to_csv::{flatten_record, process_to_csv, write_csv},
 };
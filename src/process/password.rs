@@ -0,0 +1,154 @@
+use crate::get_reader;
+use serde::Serialize;
+use serde_json::Value;
+use std::io::{BufRead, BufReader};
+use zxcvbn::{time_estimates::CrackTimes, zxcvbn};
+
+use super::csv_table::{render_table, terminal_width};
+
+/// zxcvbn's verdict on one candidate password.
+#[derive(Debug, Serialize)]
+pub struct PasswordReport {
+    /// 1-based line number in the input, the password itself is never reported.
+    pub line: usize,
+    pub score: u8,
+    pub guesses: u64,
+    pub guesses_log10: f64,
+    pub crack_times: CrackTimeReport,
+    pub warning: Option<String>,
+    pub suggestions: Vec<String>,
+}
+
+/// Estimated time to crack under each of zxcvbn's attack scenarios.
+#[derive(Debug, Serialize)]
+pub struct CrackTimeReport {
+    pub online_throttling: String,
+    pub online_no_throttling: String,
+    pub offline_slow_hashing: String,
+    pub offline_fast_hashing: String,
+}
+
+/// Check every non-empty line of `input` as a password.
+pub fn process_password_check(
+    input: &str,
+    user_inputs: &[String],
+) -> anyhow::Result<Vec<PasswordReport>> {
+    let user_inputs: Vec<&str> = user_inputs.iter().map(String::as_str).collect();
+    let reader = BufReader::new(get_reader(input)?);
+    let mut reports = Vec::new();
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let password = line.trim_end_matches('\r');
+        if password.is_empty() {
+            continue;
+        }
+        let estimate = zxcvbn(password, &user_inputs)?;
+        let feedback = estimate.feedback().as_ref();
+        reports.push(PasswordReport {
+            line: i + 1,
+            score: estimate.score(),
+            guesses: estimate.guesses(),
+            guesses_log10: estimate.guesses_log10(),
+            crack_times: estimate.crack_times().into(),
+            warning: feedback.and_then(|f| f.warning()).map(|w| w.to_string()),
+            suggestions: feedback
+                .map(|f| f.suggestions().iter().map(|s| s.to_string()).collect())
+                .unwrap_or_default(),
+        });
+    }
+    Ok(reports)
+}
+
+impl From<CrackTimes> for CrackTimeReport {
+    fn from(times: CrackTimes) -> Self {
+        Self {
+            online_throttling: times.online_throttling_100_per_hour().to_string(),
+            online_no_throttling: times.online_no_throttling_10_per_second().to_string(),
+            offline_slow_hashing: times.offline_slow_hashing_1e4_per_second().to_string(),
+            offline_fast_hashing: times.offline_fast_hashing_1e10_per_second().to_string(),
+        }
+    }
+}
+
+/// Render the reports as a table, followed by the full warning and suggestions of each line,
+/// which would be truncated as table cells.
+pub fn render_password_report(reports: &[PasswordReport]) -> String {
+    let headers: Vec<String> = [
+        "line",
+        "score",
+        "guesses",
+        "online throttled",
+        "online",
+        "offline slow",
+        "offline fast",
+    ]
+    .iter()
+    .map(|h| h.to_string())
+    .collect();
+    let rows: Vec<Vec<Value>> = reports
+        .iter()
+        .map(|r| {
+            vec![
+                Value::from(r.line),
+                Value::from(r.score),
+                Value::from(r.guesses),
+                Value::from(r.crack_times.online_throttling.as_str()),
+                Value::from(r.crack_times.online_no_throttling.as_str()),
+                Value::from(r.crack_times.offline_slow_hashing.as_str()),
+                Value::from(r.crack_times.offline_fast_hashing.as_str()),
+            ]
+        })
+        .collect();
+    let mut report = render_table(&headers, &rows, 60, terminal_width());
+    for r in reports {
+        if r.warning.is_none() && r.suggestions.is_empty() {
+            continue;
+        }
+        report.push_str(&format!("\nline {}:\n", r.line));
+        if let Some(warning) = &r.warning {
+            report.push_str(&format!("  warning: {}\n", warning));
+        }
+        for suggestion in &r.suggestions {
+            report.push_str(&format!("  suggestion: {}\n", suggestion));
+        }
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_password_check() -> anyhow::Result<()> {
+        let path = std::env::temp_dir().join("rcli_test_passwords.txt");
+        fs::write(
+            &path,
+            "password\r\n\ncorrect-horse-battery-staple\njuventus1897\n",
+        )?;
+        let path = path.to_str().unwrap();
+
+        let reports = process_password_check(path, &[])?;
+        assert_eq!(reports.len(), 3);
+        assert_eq!(reports[0].line, 1);
+        assert_eq!(reports[0].score, 0);
+        assert!(reports[0].warning.is_some());
+        assert!(!reports[0].suggestions.is_empty());
+        assert_eq!(reports[1].line, 3);
+        assert_eq!(reports[1].score, 4);
+
+        // 用户相关的词会降低评分
+        let personal = process_password_check(path, &["juventus".to_string()])?;
+        assert!(personal[2].guesses < reports[2].guesses);
+
+        // 警告和建议完整输出，不随表格截断
+        let rendered = render_password_report(&reports);
+        let warning = reports[0].warning.as_deref().unwrap_or_default();
+        assert!(rendered.contains(&format!("line 1:\n  warning: {}\n", warning)));
+        for suggestion in &reports[0].suggestions {
+            assert!(rendered.contains(&format!("  suggestion: {}\n", suggestion)));
+        }
+        Ok(())
+    }
+}
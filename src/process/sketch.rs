@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+
+/// HyperLogLog 的寄存器位数，2^14 个寄存器，标准误差约 0.8%
+const HLL_PRECISION: u32 = 14;
+
+/// Estimates the number of distinct values from their 64-bit hashes.
+#[derive(Debug)]
+pub(super) struct HyperLogLog {
+    registers: Vec<u8>,
+}
+
+impl HyperLogLog {
+    pub fn new() -> Self {
+        Self {
+            registers: vec![0; 1 << HLL_PRECISION],
+        }
+    }
+
+    pub fn insert(&mut self, hash: u64) {
+        let index = (hash >> (64 - HLL_PRECISION)) as usize;
+        // 剩余的位中第一个 1 的位置，补一个哨兵位保证不超过寄存器范围
+        let rest = (hash << HLL_PRECISION) | (1 << (HLL_PRECISION - 1));
+        let rank = rest.leading_zeros() as u8 + 1;
+        self.registers[index] = self.registers[index].max(rank);
+    }
+
+    pub fn estimate(&self) -> u64 {
+        let m = self.registers.len() as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / m);
+        let sum: f64 = self.registers.iter().map(|&r| 2f64.powi(-(r as i32))).sum();
+        let estimate = alpha * m * m / sum;
+        let zeros = self.registers.iter().filter(|&&r| r == 0).count();
+        // 基数较小时改用线性计数
+        if estimate <= 2.5 * m && zeros > 0 {
+            (m * (m / zeros as f64).ln()).round() as u64
+        } else {
+            estimate.round() as u64
+        }
+    }
+}
+
+/// Estimates the median in constant space with the P² algorithm (Jain & Chlamtac, 1985).
+#[derive(Debug, Default)]
+pub(super) struct P2Median {
+    /// 五个标记的高度
+    heights: [f64; 5],
+    /// 标记的实际位置
+    positions: [f64; 5],
+    /// 标记的期望位置
+    desired: [f64; 5],
+    count: usize,
+}
+
+impl P2Median {
+    const INCREMENTS: [f64; 5] = [0.0, 0.25, 0.5, 0.75, 1.0];
+
+    pub fn insert(&mut self, x: f64) {
+        if self.count < 5 {
+            self.heights[self.count] = x;
+            self.count += 1;
+            if self.count == 5 {
+                self.heights.sort_by(f64::total_cmp);
+                self.positions = [1.0, 2.0, 3.0, 4.0, 5.0];
+                self.desired = [1.0, 2.0, 3.0, 4.0, 5.0];
+            }
+            return;
+        }
+        self.count += 1;
+
+        let k = if x < self.heights[0] {
+            self.heights[0] = x;
+            0
+        } else if x >= self.heights[4] {
+            self.heights[4] = x;
+            3
+        } else {
+            (0..4)
+                .find(|&i| x < self.heights[i + 1])
+                .expect("x is below the last marker")
+        };
+        for position in &mut self.positions[k + 1..] {
+            *position += 1.0;
+        }
+        for (desired, increment) in self.desired.iter_mut().zip(Self::INCREMENTS) {
+            *desired += increment;
+        }
+
+        for i in 1..4 {
+            let d = self.desired[i] - self.positions[i];
+            let (q, n) = (&mut self.heights, &mut self.positions);
+            if (d >= 1.0 && n[i + 1] - n[i] > 1.0) || (d <= -1.0 && n[i - 1] - n[i] < -1.0) {
+                let d = d.signum();
+                let parabolic = q[i]
+                    + d / (n[i + 1] - n[i - 1])
+                        * ((n[i] - n[i - 1] + d) * (q[i + 1] - q[i]) / (n[i + 1] - n[i])
+                            + (n[i + 1] - n[i] - d) * (q[i] - q[i - 1]) / (n[i] - n[i - 1]));
+                q[i] = if q[i - 1] < parabolic && parabolic < q[i + 1] {
+                    parabolic
+                } else {
+                    let j = if d > 0.0 { i + 1 } else { i - 1 };
+                    q[i] + d * (q[j] - q[i]) / (n[j] - n[i])
+                };
+                n[i] += d;
+            }
+        }
+    }
+
+    pub fn estimate(&self) -> Option<f64> {
+        match self.count {
+            0 => None,
+            1..=4 => {
+                let mut values = self.heights[..self.count].to_vec();
+                values.sort_by(f64::total_cmp);
+                Some(values[values.len() / 2])
+            }
+            _ => Some(self.heights[2]),
+        }
+    }
+}
+
+/// Finds the most frequent values with at most `capacity` counters (Misra-Gries).
+///
+/// Once the counters overflow, every reported count is a lower bound that is off by at most
+/// `total / (capacity + 1)`, and any value seen more often than that is still reported.
+#[derive(Debug)]
+pub(super) struct FrequentValues {
+    counts: HashMap<String, u64>,
+    capacity: usize,
+    overflowed: bool,
+}
+
+impl FrequentValues {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            counts: HashMap::new(),
+            capacity,
+            overflowed: false,
+        }
+    }
+
+    pub fn insert(&mut self, value: &str) {
+        if let Some(n) = self.counts.get_mut(value) {
+            *n += 1;
+        } else if self.counts.len() < self.capacity {
+            self.counts.insert(value.to_string(), 1);
+        } else {
+            // 计数器已满：所有计数减一，新值也随之抵消
+            self.overflowed = true;
+            self.counts.retain(|_, n| {
+                *n -= 1;
+                *n > 0
+            });
+        }
+    }
+
+    /// Whether the counts are estimates rather than exact.
+    pub fn is_approximate(&self) -> bool {
+        self.overflowed
+    }
+
+    pub fn into_counts(self) -> HashMap<String, u64> {
+        self.counts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        collections::hash_map::DefaultHasher,
+        hash::{Hash, Hasher},
+    };
+
+    #[test]
+    fn test_hyperloglog() {
+        let mut hll = HyperLogLog::new();
+        for i in 0..200_000u64 {
+            let mut hasher = DefaultHasher::new();
+            (i % 100_000).hash(&mut hasher);
+            hll.insert(hasher.finish());
+        }
+        let estimate = hll.estimate() as f64;
+        assert!(
+            (estimate - 100_000.0).abs() / 100_000.0 < 0.03,
+            "{}",
+            estimate
+        );
+    }
+
+    #[test]
+    fn test_p2_median() {
+        let mut median = P2Median::default();
+        assert_eq!(median.estimate(), None);
+        for x in [3.0, 1.0, 2.0] {
+            median.insert(x);
+        }
+        assert_eq!(median.estimate(), Some(2.0));
+
+        let mut median = P2Median::default();
+        // 0..10000 打乱顺序后插入
+        for i in 0..10_000u64 {
+            median.insert((i * 7919 % 10_000) as f64);
+        }
+        let estimate = median.estimate().unwrap();
+        assert!((estimate - 5_000.0).abs() < 100.0, "{}", estimate);
+    }
+
+    #[test]
+    fn test_frequent_values() {
+        let mut frequent = FrequentValues::new(2);
+        for value in ["a", "b", "a", "c", "a", "d", "a"] {
+            frequent.insert(value);
+        }
+        assert!(frequent.is_approximate());
+        let counts = frequent.into_counts();
+        assert!(counts["a"] >= 4 - 7 / 3);
+        assert!(counts.len() <= 2);
+    }
+}
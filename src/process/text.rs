@@ -5,7 +5,7 @@ use ed25519_dalek::{Signer, SigningKey};
 use rand::rngs::OsRng;
 use std::{fs, io::Read, path::Path};
 
-use crate::{get_reader, TextSignFormat};
+use crate::{get_input_reader, CharsetOpts, TextSignFormat};
 
 use super::process_genpass;
 
@@ -40,7 +40,7 @@ pub struct Ed25519Verifier {
 }
 
 pub fn process_text_sign(input: &str, key: &str, format: TextSignFormat) -> Result<String> {
-    let mut reader = get_reader(input)?;
+    let mut reader = get_input_reader(input)?;
     let signed = match format {
         TextSignFormat::Blake3 => {
             let signer = Blake3::load(key)?;
@@ -61,7 +61,7 @@ pub fn process_text_verify(
     format: TextSignFormat,
     sign: &str,
 ) -> Result<bool> {
-    let mut reader = get_reader(input)?;
+    let mut reader = get_input_reader(input)?;
 
     let signed = URL_SAFE_NO_PAD.decode(sign)?;
 
@@ -121,7 +121,7 @@ impl KeyLoader for Blake3 {
 }
 impl KeyGenerator for Blake3 {
     fn generate() -> Result<Vec<Vec<u8>>> {
-        let key = process_genpass(32, false, false, false, false)?;
+        let key = process_genpass(32, &CharsetOpts::default())?;
         let key = key.as_bytes();
         Ok(vec![key.to_vec()])
     }
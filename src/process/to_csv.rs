@@ -0,0 +1,237 @@
+use crate::{
+    cli::{ArrayMode, CsvReadOpts, OutputFormat},
+    get_data_reader, get_writer, read_records,
+};
+use csv::WriterBuilder;
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    io::{BufReader, Write},
+};
+
+/// A flattened record: dotted column names paired with their cell values, in document order.
+type FlatRow = Vec<(String, String)>;
+
+/// Convert an array of objects in any of the `OutputFormat` formats back to CSV.
+pub fn process_to_csv(
+    input: &str,
+    output: &str,
+    from: OutputFormat,
+    opts: &CsvReadOpts,
+    arrays: ArrayMode,
+    separator: &str,
+) -> anyhow::Result<()> {
+    let records = read_records(BufReader::new(get_data_reader(input)?), from)?;
+    let mut writer = get_writer(output)?;
+    write_csv(&records, &mut writer, opts, arrays, separator)?;
+    writer.finish()
+}
+
+/// Write records as CSV, with the union of their flattened columns as the header.
+pub fn write_csv(
+    records: &[Value],
+    writer: impl Write,
+    opts: &CsvReadOpts,
+    arrays: ArrayMode,
+    separator: &str,
+) -> anyhow::Result<()> {
+    let mut rows: Vec<FlatRow> = Vec::new();
+    for record in records {
+        rows.extend(flatten_record(record, arrays, separator)?);
+    }
+
+    // 所有记录的列的并集，按首次出现的顺序排列
+    let mut headers: Vec<&str> = Vec::new();
+    let mut positions = HashMap::new();
+    for (key, _) in rows.iter().flatten() {
+        if !positions.contains_key(key.as_str()) {
+            positions.insert(key.as_str(), headers.len());
+            headers.push(key);
+        }
+    }
+
+    let mut writer = WriterBuilder::new()
+        .delimiter(opts.delimiter as u8)
+        .from_writer(writer);
+    if opts.header {
+        writer.write_record(&headers)?;
+    }
+    for row in &rows {
+        let mut cells = vec![""; headers.len()];
+        for (key, value) in row {
+            cells[positions[key.as_str()]] = value;
+        }
+        writer.write_record(&cells)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Flatten a record into one or more rows, nested objects become dotted column names.
+///
+/// When exploding, arrays side by side in an object are zipped, so they must have the same
+/// length; scalars and one-item arrays are repeated on every row.
+pub fn flatten_record(
+    record: &Value,
+    arrays: ArrayMode,
+    separator: &str,
+) -> anyhow::Result<Vec<FlatRow>> {
+    match record {
+        Value::Object(_) => flatten(record, "", arrays, separator),
+        // 非对象的记录放在 value 列中
+        _ => flatten(record, "value", arrays, separator),
+    }
+}
+
+fn flatten(
+    value: &Value,
+    prefix: &str,
+    arrays: ArrayMode,
+    separator: &str,
+) -> anyhow::Result<Vec<FlatRow>> {
+    Ok(match value {
+        Value::Object(map) => {
+            let mut rows = vec![Vec::new()];
+            for (key, value) in map {
+                let key = if prefix.is_empty() {
+                    key.to_string()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                let children = flatten(value, &key, arrays, separator)?;
+                // 并列的数组按下标一一对应，而不是做笛卡尔积
+                if children.len() > 1 && rows.len() > 1 && children.len() != rows.len() {
+                    anyhow::bail!(
+                        "cannot explode {} with {} items next to arrays with {} items",
+                        key,
+                        children.len(),
+                        rows.len()
+                    );
+                }
+                let len = rows.len().max(children.len());
+                rows = (0..len)
+                    .map(|i| {
+                        let mut row = rows[i.min(rows.len() - 1)].clone();
+                        row.extend(children[i.min(children.len() - 1)].iter().cloned());
+                        row
+                    })
+                    .collect();
+            }
+            rows
+        }
+        Value::Array(items) => match arrays {
+            ArrayMode::Explode if !items.is_empty() => {
+                let mut rows = Vec::new();
+                for item in items {
+                    rows.extend(flatten(item, prefix, arrays, separator)?);
+                }
+                rows
+            }
+            ArrayMode::Explode => vec![vec![(prefix.to_string(), String::new())]],
+            ArrayMode::Join => {
+                let cell = if items.iter().all(|v| !v.is_object() && !v.is_array()) {
+                    items
+                        .iter()
+                        .map(scalar_to_string)
+                        .collect::<Vec<_>>()
+                        .join(separator)
+                } else {
+                    // 对象数组无法拼接，保留为 JSON 字符串
+                    value.to_string()
+                };
+                vec![vec![(prefix.to_string(), cell)]]
+            }
+        },
+        _ => vec![vec![(prefix.to_string(), scalar_to_string(value))]],
+    })
+}
+
+fn scalar_to_string(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        _ => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn row(cells: &[(&str, &str)]) -> FlatRow {
+        cells
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_flatten_nested_objects() -> anyhow::Result<()> {
+        let record =
+            json!({"name": "Buffon", "address": {"city": "Turin", "zip": null}, "age": 41});
+        assert_eq!(
+            flatten_record(&record, ArrayMode::Join, ";")?,
+            vec![row(&[
+                ("name", "Buffon"),
+                ("address.city", "Turin"),
+                ("address.zip", ""),
+                ("age", "41")
+            ])]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_flatten_arrays() -> anyhow::Result<()> {
+        let record = json!({"name": "Dybala", "tags": ["fast", "left"]});
+        assert_eq!(
+            flatten_record(&record, ArrayMode::Join, "|")?,
+            vec![row(&[("name", "Dybala"), ("tags", "fast|left")])]
+        );
+        assert_eq!(
+            flatten_record(&record, ArrayMode::Explode, ";")?,
+            vec![
+                row(&[("name", "Dybala"), ("tags", "fast")]),
+                row(&[("name", "Dybala"), ("tags", "left")])
+            ]
+        );
+
+        // 并列的数组按下标对应，长度不同时报错
+        let record = json!({"tags": ["fast", "left"], "goals": [1, 2], "club": ["Juventus"]});
+        assert_eq!(
+            flatten_record(&record, ArrayMode::Explode, ";")?,
+            vec![
+                row(&[("tags", "fast"), ("goals", "1"), ("club", "Juventus")]),
+                row(&[("tags", "left"), ("goals", "2"), ("club", "Juventus")])
+            ]
+        );
+        let record = json!({"tags": ["fast", "left"], "goals": [1, 2, 3]});
+        assert!(flatten_record(&record, ArrayMode::Explode, ";").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_process_to_csv_header_union() -> anyhow::Result<()> {
+        let dir = std::env::temp_dir();
+        let input = dir.join("rcli_test_to_csv.ndjson");
+        let output = dir.join("rcli_test_to_csv.csv");
+        std::fs::write(
+            &input,
+            "{\"id\":1,\"name\":\"Buffon\"}\n{\"id\":2,\"club\":{\"name\":\"Juventus\"}}\n",
+        )?;
+        process_to_csv(
+            &input.to_string_lossy(),
+            &output.to_string_lossy(),
+            OutputFormat::Ndjson,
+            &CsvReadOpts::default(),
+            ArrayMode::Join,
+            ";",
+        )?;
+        assert_eq!(
+            std::fs::read_to_string(&output)?,
+            "id,name,club.name\n1,Buffon,\n2,,Juventus\n"
+        );
+        Ok(())
+    }
+}
@@ -1,17 +1,228 @@
 use anyhow::Result;
 use std::{
     fs::File,
-    io::{stdin, BufReader, Read},
+    io::{self, stdin, stdout, BufRead, BufReader, BufWriter, IsTerminal, Read, StdoutLock, Write},
+    path::Path,
 };
 
+/// Compression of an input or output file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+    Bzip2,
+}
+
+impl Compression {
+    /// Pick the compression from a file extension such as `.csv.gz`.
+    pub fn from_path(path: &str) -> Self {
+        match Path::new(path).extension().and_then(|e| e.to_str()) {
+            Some("gz" | "gzip") => Compression::Gzip,
+            Some("zst" | "zstd") => Compression::Zstd,
+            Some("bz2") => Compression::Bzip2,
+            _ => Compression::None,
+        }
+    }
+
+    /// Recognize compressed data by its leading magic bytes.
+    pub fn from_magic(bytes: &[u8]) -> Self {
+        if bytes.starts_with(&[0x1f, 0x8b]) {
+            Compression::Gzip
+        } else if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Compression::Zstd
+        } else if bytes.starts_with(b"BZh") {
+            Compression::Bzip2
+        } else {
+            Compression::None
+        }
+    }
+}
+
+/// Open `input` for reading, `-` reads stdin. The bytes are returned exactly as stored.
 pub fn get_reader(input: &str) -> Result<Box<dyn Read>> {
+    Ok(open(input)?)
+}
+
+/// Like [`get_reader`], but decompresses gzip, zstd and bzip2 input, chosen by extension or
+/// by magic bytes. Only for data files (csv, convert, query), never for signed or encoded bytes.
+pub fn get_data_reader(input: &str) -> Result<Box<dyn Read>> {
+    decompress(open(input)?, Compression::from_path(input))
+}
+
+/// Like [`get_reader`], but decompresses files ending in `.gz`, `.zst` or `.bz2`. Other input,
+/// stdin included, is read byte for byte, so it suits signed or encoded bytes (base64, text).
+pub fn get_input_reader(input: &str) -> Result<Box<dyn Read>> {
+    match Compression::from_path(input) {
+        Compression::None => get_reader(input),
+        compression => decompress(open(input)?, compression),
+    }
+}
+
+fn open(input: &str) -> Result<Box<dyn BufRead>> {
     // 用Box和syn还有Trait消除多种类型
-    let reader: Box<dyn Read> = if input == "-" {
-        println!("请输入内容 (按Ctrl+D或Ctrl+Z结束):"); // 更新提示信息
+    let reader: Box<dyn BufRead> = if input == "-" {
+        // 提示写到 stderr，且只在交互输入时显示，避免混入管道中的输出
+        if stdin().is_terminal() {
+            eprintln!("请输入内容 (按Ctrl+D或Ctrl+Z结束):");
+        }
         Box::new(BufReader::new(stdin()))
     } else {
-        Box::new(File::open(input)?)
+        Box::new(BufReader::new(File::open(input)?))
     };
+    Ok(reader)
+}
 
+/// 扩展名优先，否则根据开头的魔数判断是否压缩
+fn decompress(mut reader: Box<dyn BufRead>, compression: Compression) -> Result<Box<dyn Read>> {
+    let compression = match compression {
+        Compression::None => Compression::from_magic(reader.fill_buf()?),
+        compression => compression,
+    };
+    let reader: Box<dyn Read> = match compression {
+        Compression::None => reader,
+        Compression::Gzip => Box::new(flate2::bufread::MultiGzDecoder::new(reader)),
+        Compression::Zstd => Box::new(zstd::Decoder::with_buffer(reader)?),
+        Compression::Bzip2 => Box::new(bzip2::bufread::MultiBzDecoder::new(reader)),
+    };
     Ok(reader)
 }
+
+/// A file or stdout opened by [`get_writer`].
+///
+/// Call [`Output::finish`] after the last write: it writes the trailer of compressed files and
+/// reports any error doing so, which dropping the output would silently discard.
+pub struct Output(Sink);
+
+enum Sink {
+    Stdout(BufWriter<StdoutLock<'static>>),
+    File(BufWriter<File>),
+    Gzip(BufWriter<flate2::write::GzEncoder<File>>),
+    Zstd(BufWriter<zstd::Encoder<'static, File>>),
+    Bzip2(BufWriter<bzip2::write::BzEncoder<File>>),
+}
+
+/// Open `output` for writing, `-` writes to stdout. Files ending in `.gz`, `.zst` or `.bz2` are
+/// compressed accordingly.
+pub fn get_writer(output: &str) -> Result<Output> {
+    if output == "-" {
+        return Ok(Output(Sink::Stdout(BufWriter::new(stdout().lock()))));
+    }
+
+    let file = File::create(output)?;
+    let sink = match Compression::from_path(output) {
+        Compression::None => Sink::File(BufWriter::new(file)),
+        Compression::Gzip => Sink::Gzip(BufWriter::new(flate2::write::GzEncoder::new(
+            file,
+            flate2::Compression::default(),
+        ))),
+        Compression::Zstd => Sink::Zstd(BufWriter::new(zstd::Encoder::new(file, 0)?)),
+        Compression::Bzip2 => Sink::Bzip2(BufWriter::new(bzip2::write::BzEncoder::new(
+            file,
+            bzip2::Compression::default(),
+        ))),
+    };
+    Ok(Output(sink))
+}
+
+impl Output {
+    /// Flush everything and finish the compressed stream.
+    pub fn finish(self) -> Result<()> {
+        match self.0 {
+            Sink::Stdout(mut writer) => writer.flush()?,
+            Sink::File(writer) => {
+                writer.into_inner().map_err(|e| e.into_error())?;
+            }
+            Sink::Gzip(writer) => {
+                writer.into_inner().map_err(|e| e.into_error())?.finish()?;
+            }
+            Sink::Zstd(writer) => {
+                writer.into_inner().map_err(|e| e.into_error())?.finish()?;
+            }
+            Sink::Bzip2(writer) => {
+                writer.into_inner().map_err(|e| e.into_error())?.finish()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn inner(&mut self) -> &mut dyn Write {
+        match &mut self.0 {
+            Sink::Stdout(writer) => writer,
+            Sink::File(writer) => writer,
+            Sink::Gzip(writer) => writer,
+            Sink::Zstd(writer) => writer,
+            Sink::Bzip2(writer) => writer,
+        }
+    }
+}
+
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner().write(buf)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.inner().write_all(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner().flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compressed_round_trip() -> Result<()> {
+        let content = b"name,age\nBuffon,41\n";
+        for ext in ["csv", "csv.gz", "csv.zst", "csv.bz2"] {
+            let path = std::env::temp_dir().join(format!("rcli_test_compress.{}", ext));
+            let path = path.to_string_lossy().to_string();
+            let mut writer = get_writer(&path)?;
+            writer.write_all(content)?;
+            writer.finish()?;
+            let raw = std::fs::read(&path)?;
+            assert_eq!(Compression::from_magic(&raw), Compression::from_path(&path));
+
+            let mut buf = Vec::new();
+            get_data_reader(&path)?.read_to_end(&mut buf)?;
+            assert_eq!(buf, content);
+            let mut buf = Vec::new();
+            get_input_reader(&path)?.read_to_end(&mut buf)?;
+            assert_eq!(buf, content);
+
+            // get_reader 原样返回文件内容
+            let mut buf = Vec::new();
+            get_reader(&path)?.read_to_end(&mut buf)?;
+            assert_eq!(buf, raw);
+
+            // 没有扩展名时根据魔数识别
+            let renamed = format!("{}.data", path);
+            std::fs::rename(&path, &renamed)?;
+            let mut buf = Vec::new();
+            get_data_reader(&renamed)?.read_to_end(&mut buf)?;
+            assert_eq!(buf, content);
+            // get_input_reader 只看扩展名
+            let mut buf = Vec::new();
+            get_input_reader(&renamed)?.read_to_end(&mut buf)?;
+            assert_eq!(buf, raw);
+            std::fs::remove_file(renamed)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_get_reader_keeps_bzip2_lookalike() -> Result<()> {
+        let path = std::env::temp_dir().join("rcli_test_bzh.txt");
+        std::fs::write(&path, b"BZh9secret\n")?;
+        let path = path.to_string_lossy().to_string();
+        let mut buf = Vec::new();
+        get_reader(&path)?.read_to_end(&mut buf)?;
+        assert_eq!(buf, b"BZh9secret\n");
+        std::fs::remove_file(path)?;
+        Ok(())
+    }
+}